@@ -0,0 +1,269 @@
+use std::{io, time::{SystemTime, UNIX_EPOCH}};
+
+use image::Rgba;
+
+use crate::{colors::{closest_in_palette, palette_codes, PaletteCodes}, encoder::{push_u8, ColorDepth, Encoder}, grid::Grid};
+
+/// The 16 colors of the VGA text mode, in the order of their codes.
+const VGA_PALETTE: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00], [0xaa, 0x00, 0x00], [0x00, 0xaa, 0x00], [0xaa, 0x55, 0x00],
+    [0x00, 0x00, 0xaa], [0xaa, 0x00, 0xaa], [0x00, 0xaa, 0xaa], [0xaa, 0xaa, 0xaa],
+    [0x55, 0x55, 0x55], [0xff, 0x55, 0x55], [0x55, 0xff, 0x55], [0xff, 0xff, 0x55],
+    [0x55, 0x55, 0xff], [0xff, 0x55, 0xff], [0x55, 0xff, 0xff], [0xff, 0xff, 0xff],
+];
+
+/// Marks the end of the art, so that the SAUCE record isn't displayed.
+const EOF: u8 = 0x1a;
+
+/// Emits an ANSI art file (`.ans`) in code page 437, with a SAUCE record describing it.
+///
+/// Colors are reduced to the 16 VGA colors. Bright backgrounds use iCE colors, which the SAUCE flags announce.
+/// Characters missing from CP437 are approximated: braille by its density as shade blocks, anything else as `?`.
+/// The luminance, edges and shapes modes only use ASCII, so they are not affected.
+pub struct AnsEncoder {
+    pub depth: ColorDepth,
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    /// Font name as defined by the SAUCE specification.
+    pub font: String,
+    /// Creation date as `CCYYMMDD`.
+    pub date: String,
+}
+
+impl AnsEncoder {
+    /// Width of the screen of ANSI art viewers, which wrap lines that fill it. Wider art can't be encoded.
+    pub const MAX_WIDTH: u32 = 80;
+
+    /// Create an encoder for art created today.
+    pub fn new(depth: ColorDepth) -> Self {
+        AnsEncoder {
+            depth,
+            title: String::new(),
+            author: String::new(),
+            group: String::new(),
+            font: String::from("IBM VGA"),
+            date: today(),
+        }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = author.into();
+        self
+    }
+
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.group = group.into();
+        self
+    }
+
+    pub fn with_font(mut self, font: impl Into<String>) -> Self {
+        self.font = font.into();
+        self
+    }
+
+    /// Append the 128 byte SAUCE record of art of the given size.
+    fn push_sauce(&self, buf: &mut Vec<u8>, grid: &Grid, file_size: usize) {
+        buf.extend_from_slice(b"SAUCE00");
+        push_field(buf, &self.title, 35, b' ');
+        push_field(buf, &self.author, 20, b' ');
+        push_field(buf, &self.group, 20, b' ');
+        push_field(buf, &self.date, 8, b' ');
+        buf.extend_from_slice(&(file_size as u32).to_le_bytes());
+        // Character data, ANSi file.
+        buf.extend_from_slice(&[1, 1]);
+        buf.extend_from_slice(&(grid.width() as u16).to_le_bytes());
+        buf.extend_from_slice(&(grid.height() as u16).to_le_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        // No comments.
+        buf.push(0);
+        // iCE colors, 8 pixel font, legacy aspect ratio.
+        buf.push(0b0000_1011);
+        push_field(buf, &self.font, 22, 0);
+    }
+}
+
+/// Append a text field of the SAUCE record in CP437, truncated or padded to `len` bytes.
+fn push_field(buf: &mut Vec<u8>, text: &str, len: usize, padding: u8) {
+    let start = buf.len();
+    buf.extend(text.chars().map(to_cp437).take(len));
+    buf.resize(start + len, padding);
+}
+
+/// The CP437 code of a character, or of a character that looks similar.
+fn to_cp437(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '░' => 0xb0,
+        '▒' => 0xb1,
+        '▓' => 0xb2,
+        '█' => 0xdb,
+        '▄' => 0xdc,
+        '▌' => 0xdd,
+        '▐' => 0xde,
+        '▀' => 0xdf,
+        '\u{2800}'..='\u{28ff}' => match (c as u32 - 0x2800).count_ones() {
+            0 => b' ',
+            1..=2 => 0xb0,
+            3..=5 => 0xb1,
+            6..=7 => 0xb2,
+            _ => 0xdb,
+        },
+        _ => b'?',
+    }
+}
+
+/// The code of the closest VGA color. Translucent colors are darkened first, like the 256 color codes of the terminal output.
+fn vga_code(col: Rgba<u8>) -> u8 {
+    closest_in_palette(col, &VGA_PALETTE) as u8
+}
+
+/// Today's date as `CCYYMMDD`.
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86400) as i64;
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}{month:02}{day:02}")
+}
+
+impl Encoder for AnsEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        if grid.width() > Self::MAX_WIDTH {
+            let message = format!("ANSI art is at most {} columns wide, got {}", Self::MAX_WIDTH, grid.width());
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        let start = buf.len();
+
+        // Colors as VGA codes. `None` is the default, light gray on black.
+        let mut state: PaletteCodes = (None, None);
+        for row in grid.rows() {
+            for cell in row {
+                let (fg, bg) = palette_codes(cell, self.depth, state, vga_code, vga_code);
+
+                // Every change starts from a reset, which all viewers understand.
+                if (fg, bg) != state {
+                    buf.extend_from_slice(b"\x1b[0");
+                    if let Some(fg) = fg {
+                        buf.extend_from_slice(if fg >= 8 {b";1;3"} else {b";3"});
+                        push_u8(buf, fg % 8);
+                    }
+                    if let Some(bg) = bg {
+                        buf.extend_from_slice(if bg >= 8 {b";5;4"} else {b";4"});
+                        push_u8(buf, bg % 8);
+                    }
+                    buf.push(b'm');
+                    state = (fg, bg);
+                }
+                buf.push(to_cp437(cell.glyph));
+            }
+            if state != (None, None) {
+                buf.extend_from_slice(b"\x1b[0m");
+                state = (None, None);
+            }
+            // A full line already moved the cursor to the next one.
+            if row.len() != Self::MAX_WIDTH as usize {
+                buf.extend_from_slice(b"\r\n");
+            }
+        }
+
+        let file_size = buf.len() - start;
+        buf.push(EOF);
+        self.push_sauce(buf, grid, file_size);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::Cell;
+
+    use super::*;
+
+    fn encode(encoder: &AnsEncoder, grid: &Grid) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encoder.encode_into(grid, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn sauce_record_describes_the_art() {
+        let encoder = AnsEncoder { date: String::from("20261018"), ..AnsEncoder::new(ColorDepth::Ansi256) }
+            .with_title("Mandelbrot")
+            .with_author("Gabriel");
+        let buf = encode(&encoder, &Grid::new(3, 2));
+
+        let (art, sauce) = buf.split_at(buf.len() - 128);
+        assert_eq!(art, b"   \r\n   \r\n\x1a");
+        assert_eq!(&sauce[0..7], b"SAUCE00");
+        assert_eq!(&sauce[7..42], format!("{:35}", "Mandelbrot").as_bytes());
+        assert_eq!(&sauce[42..62], format!("{:20}", "Gabriel").as_bytes());
+        assert_eq!(&sauce[62..82], [b' '; 20]);
+        assert_eq!(&sauce[82..90], b"20261018");
+        // File size without the EOF byte and the record.
+        assert_eq!(&sauce[90..94], 10u32.to_le_bytes());
+        // Character data, ANSi file.
+        assert_eq!(&sauce[94..96], [1, 1]);
+        // TInfo1 and TInfo2 are the width and height, TInfo3 and TInfo4 are unused.
+        assert_eq!(&sauce[96..98], 3u16.to_le_bytes());
+        assert_eq!(&sauce[98..100], 2u16.to_le_bytes());
+        assert_eq!(&sauce[100..104], [0; 4]);
+        assert_eq!(sauce[104], 0);
+        assert_eq!(sauce[105], 0b1011);
+        assert_eq!(&sauce[106..128], b"IBM VGA\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+    }
+
+    #[test]
+    fn sauce_fields_are_truncated() {
+        let buf = encode(&AnsEncoder::new(ColorDepth::Monochrome).with_title("x".repeat(40)), &Grid::new(1, 1));
+        let sauce = &buf[buf.len() - 128..];
+        assert_eq!(&sauce[7..42], [b'x'; 35]);
+        assert_eq!(&sauce[42..62], [b' '; 20]);
+    }
+
+    #[test]
+    fn full_lines_have_no_line_break() {
+        let buf = encode(&AnsEncoder::new(ColorDepth::Monochrome), &Grid::new(80, 2));
+        let art = &buf[..buf.len() - 129];
+        assert_eq!(art, [b' '; 160]);
+        assert_eq!(&buf[buf.len() - 128 + 90..buf.len() - 128 + 94], 160u32.to_le_bytes());
+    }
+
+    #[test]
+    fn wider_art_is_rejected() {
+        let mut buf = Vec::new();
+        let error = AnsEncoder::new(ColorDepth::Monochrome).encode_into(&Grid::new(81, 1), &mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn colors_change_from_a_reset() {
+        let mut grid = Grid::new(3, 1);
+        grid.set(0, 0, Cell::new('#', Rgba([255, 255, 255, 255]), Some(Rgba([0, 0, 170, 255]))));
+        grid.set(1, 0, Cell::new('#', Rgba([255, 255, 255, 255]), Some(Rgba([0, 0, 170, 255]))));
+        grid.set(2, 0, Cell::new('#', Rgba([170, 0, 0, 255]), Some(Rgba([255, 255, 85, 255]))));
+        let buf = encode(&AnsEncoder::new(ColorDepth::Ansi256), &grid);
+        assert_eq!(&buf[..buf.len() - 129], b"\x1b[0;1;37;44m##\x1b[0;31;5;43m#\x1b[0m\r\n");
+    }
+
+    #[test]
+    fn translucent_colors_are_darkened() {
+        let mut grid = Grid::new(1, 1);
+        grid.set(0, 0, Cell::new(' ', Rgba([255, 255, 255, 255]), Some(Rgba([255, 255, 255, 0]))));
+        let buf = encode(&AnsEncoder::new(ColorDepth::Ansi256), &grid);
+        assert_eq!(&buf[..buf.len() - 129], b"\x1b[0;40m \x1b[0m\r\n");
+    }
+}
@@ -0,0 +1,339 @@
+use std::{io, time::Duration};
+
+use image::Rgba;
+
+use crate::{colors::color_to_code, encoder::{push_char, push_u8, ColorDepth, Encoder}, grid::{Attributes, Cell, Frame, Grid}};
+
+/// A color as it is sent to the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TermColor {
+    Code(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// What the terminal is currently set to. `None` is the terminal default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct State {
+    fg: Option<TermColor>,
+    bg: Option<TermColor>,
+    attrs: Attributes,
+}
+
+/// Emits the grid with ansi escape sequences, using 256 color codes or truecolor.
+///
+/// Keeps track of the state of the terminal and only emits the attributes that change between cells,
+/// merged into a single sequence.
+pub struct AnsiEncoder {
+    pub depth: ColorDepth,
+    /// Truecolor colors that differ from the current one by at most this much in every channel are not emitted.
+    pub tolerance: u8,
+}
+
+impl AnsiEncoder {
+    pub fn new(depth: ColorDepth) -> Self {
+        AnsiEncoder { depth, tolerance: 0 }
+    }
+
+    /// Set the tolerance of truecolor color changes. Trades color accuracy for smaller output.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn fg_color(&self, col: Rgba<u8>) -> Option<TermColor> {
+        match self.depth {
+            ColorDepth::TrueColor => {
+                let [r, g, b, _] = col.0;
+                Some(TermColor::Rgb(r, g, b))
+            },
+            ColorDepth::Ansi256 => Some(TermColor::Code(color_to_code(col))),
+            ColorDepth::Monochrome => None,
+        }
+    }
+
+    fn bg_color(&self, col: Rgba<u8>) -> TermColor {
+        match self.depth {
+            ColorDepth::TrueColor => {
+                let [r, g, b, _] = col.0;
+                TermColor::Rgb(r, g, b)
+            },
+            // The background is emitted even in monochrome, so that the background flag works without colors.
+            ColorDepth::Ansi256 | ColorDepth::Monochrome => TermColor::Code(color_to_code(col)),
+        }
+    }
+
+    /// Whether the terminal can keep showing `current` instead of switching to `wanted`.
+    fn is_close(&self, current: Option<TermColor>, wanted: Option<TermColor>) -> bool {
+        match (current, wanted) {
+            (Some(TermColor::Rgb(r0, g0, b0)), Some(TermColor::Rgb(r1, g1, b1))) => {
+                r0.abs_diff(r1) <= self.tolerance && g0.abs_diff(g1) <= self.tolerance && b0.abs_diff(b1) <= self.tolerance
+            },
+            (current, wanted) => current == wanted,
+        }
+    }
+
+    /// Emit the changes needed to display the cell and update the state.
+    fn encode_cell(&self, buf: &mut Vec<u8>, state: &mut State, cell: &Cell) {
+        let mut params = SgrParams::new(buf);
+
+        // Attributes that were turned off.
+        if state.attrs.contains(Attributes::BOLD) && !cell.attrs.contains(Attributes::BOLD) {
+            params.push(b"22");
+        }
+        if state.attrs.contains(Attributes::UNDERLINE) && !cell.attrs.contains(Attributes::UNDERLINE) {
+            params.push(b"24");
+        }
+        // Attributes that were turned on.
+        if cell.attrs.contains(Attributes::BOLD) && !state.attrs.contains(Attributes::BOLD) {
+            params.push(b"1");
+        }
+        if cell.attrs.contains(Attributes::UNDERLINE) && !state.attrs.contains(Attributes::UNDERLINE) {
+            params.push(b"4");
+        }
+        state.attrs = cell.attrs;
+
+        // The foreground color of a space is invisible, unless it is underlined.
+        let fg_visible = cell.glyph != ' ' || cell.attrs.contains(Attributes::UNDERLINE);
+        let fg = self.fg_color(cell.fg);
+        if fg_visible && !self.is_close(state.fg, fg) {
+            params.push_color(b"38", fg);
+            state.fg = fg;
+        }
+
+        let bg = cell.bg.map(|bg| self.bg_color(bg));
+        if !self.is_close(state.bg, bg) {
+            params.push_color(b"48", bg);
+            state.bg = bg;
+        }
+
+        params.finish();
+        push_char(buf, cell.glyph);
+    }
+
+    /// Append the grid as raw terminal output that redraws it in place, for recordings.
+    ///
+    /// Moves the cursor to the top left corner first, ends lines with `\r\n` and leaves out the last line break,
+    /// so that a terminal of the size of the grid doesn't scroll.
+    pub fn encode_frame_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        let mut text = Vec::new();
+        self.encode_into(grid, &mut text)?;
+        if text.last() == Some(&b'\n') {
+            text.pop();
+        }
+
+        buf.extend_from_slice(b"\x1b[H");
+        for byte in text {
+            if byte == b'\n' {
+                buf.push(b'\r');
+            }
+            buf.push(byte);
+        }
+        Ok(())
+    }
+
+    /// Replay the frames as raw terminal output, calling `event` with every chunk and its time since the start.
+    ///
+    /// The first chunk also hides the cursor and clears the screen. Once the last frame has been on screen for its delay,
+    /// a final chunk moves the cursor below it and shows it again.
+    pub fn encode_recording(&self, frames: &[Frame], mut event: impl FnMut(Duration, &[u8]) -> io::Result<()>) -> io::Result<()> {
+        if frames.is_empty() {
+            return Ok(());
+        }
+
+        let mut time = Duration::ZERO;
+        let mut data = b"\x1b[?25l\x1b[2J".to_vec();
+        for frame in frames {
+            self.encode_frame_into(&frame.grid, &mut data)?;
+            event(time, &data)?;
+            data.clear();
+            time += frame.delay;
+        }
+        event(time, b"\r\n\x1b[?25h")
+    }
+}
+
+impl Encoder for AnsiEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        // Roughly the size of a truecolor cell, to avoid reallocating.
+        buf.reserve(grid.cells().len() * 20);
+        for row in grid.rows() {
+            let mut state = State::default();
+            for cell in row {
+                self.encode_cell(buf, &mut state, cell);
+            }
+            // Reset the terminal color to default, so that it doesn't leak into the next line.
+            if state != State::default() {
+                buf.extend_from_slice(b"\x1b[0m");
+            }
+            buf.push(b'\n');
+        }
+        Ok(())
+    }
+}
+
+/// Writes the parameters of a single SGR sequence, opening it with the first parameter.
+struct SgrParams<'a> {
+    buf: &'a mut Vec<u8>,
+    empty: bool,
+}
+
+impl<'a> SgrParams<'a> {
+    fn new(buf: &'a mut Vec<u8>) -> Self {
+        SgrParams { buf, empty: true }
+    }
+
+    fn separate(&mut self) {
+        if self.empty {
+            self.buf.extend_from_slice(b"\x1b[");
+            self.empty = false;
+        }
+        else {
+            self.buf.push(b';');
+        }
+    }
+
+    fn push(&mut self, param: &[u8]) {
+        self.separate();
+        self.buf.extend_from_slice(param);
+    }
+
+    /// Push a color with the given selector (38 for foreground, 48 for background).
+    /// `None` sets the default color.
+    fn push_color(&mut self, selector: &[u8; 2], col: Option<TermColor>) {
+        self.separate();
+        match col {
+            Some(TermColor::Code(code)) => {
+                self.buf.extend_from_slice(selector);
+                self.buf.extend_from_slice(b";5;");
+                push_u8(self.buf, code);
+            },
+            Some(TermColor::Rgb(r, g, b)) => {
+                self.buf.extend_from_slice(selector);
+                self.buf.extend_from_slice(b";2;");
+                push_u8(self.buf, r);
+                self.buf.push(b';');
+                push_u8(self.buf, g);
+                self.buf.push(b';');
+                push_u8(self.buf, b);
+            },
+            // 39 and 49 reset the foreground and background.
+            None => {
+                self.buf.push(selector[0]);
+                self.buf.push(b'9');
+            },
+        }
+    }
+
+    fn finish(self) {
+        if !self.empty {
+            self.buf.push(b'm');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{options::RenderOptions, render};
+
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn encode(encoder: &AnsiEncoder, cells: &[Cell]) -> String {
+        let mut grid = Grid::new(cells.len() as u32, 1);
+        for (x, cell) in cells.iter().enumerate() {
+            grid.set(x as u32, 0, *cell);
+        }
+        String::from_utf8(encoder.encode(&grid).unwrap()).unwrap()
+    }
+
+    fn cell(glyph: char, fg: Rgba<u8>, bg: Option<Rgba<u8>>, attrs: Attributes) -> Cell {
+        Cell { glyph, fg, bg, attrs }
+    }
+
+    #[test]
+    fn identical_neighbours_share_one_sequence() {
+        let encoder = AnsiEncoder::new(ColorDepth::Ansi256);
+        let cells = [cell('#', RED, Some(BLUE), Attributes::BOLD); 3];
+        assert_eq!(encode(&encoder, &cells), "\x1b[1;38;5;196;48;5;21m###\x1b[0m\n");
+    }
+
+    #[test]
+    fn only_changed_attributes_are_emitted() {
+        let encoder = AnsiEncoder::new(ColorDepth::Ansi256);
+        let cells = [
+            cell('a', RED, None, Attributes::BOLD),
+            cell('b', RED, None, Attributes::BOLD | Attributes::UNDERLINE),
+            cell('c', BLUE, None, Attributes::UNDERLINE),
+            cell('d', BLUE, Some(RED), Attributes::UNDERLINE),
+        ];
+        assert_eq!(encode(&encoder, &cells), "\x1b[1;38;5;196ma\x1b[4mb\x1b[22;38;5;21mc\x1b[48;5;196md\x1b[0m\n");
+    }
+
+    #[test]
+    fn foreground_is_skipped_on_spaces() {
+        let encoder = AnsiEncoder::new(ColorDepth::TrueColor);
+        let cells = [cell('#', RED, None, Attributes::NONE), cell(' ', BLUE, None, Attributes::NONE), cell('#', RED, None, Attributes::NONE)];
+        assert_eq!(encode(&encoder, &cells), "\x1b[38;2;255;0;0m# #\x1b[0m\n");
+
+        // Unless the space is underlined.
+        let cells = [cell('#', RED, None, Attributes::NONE), cell(' ', BLUE, None, Attributes::UNDERLINE)];
+        assert_eq!(encode(&encoder, &cells), "\x1b[38;2;255;0;0m#\x1b[4;38;2;0;0;255m \x1b[0m\n");
+    }
+
+    #[test]
+    fn default_colors_are_restored_with_39_and_49() {
+        let encoder = AnsiEncoder::new(ColorDepth::Ansi256);
+        let cells = [cell('#', RED, Some(BLUE), Attributes::NONE), cell('#', RED, None, Attributes::NONE)];
+        assert_eq!(encode(&encoder, &cells), "\x1b[38;5;196;48;5;21m#\x1b[49m#\x1b[0m\n");
+
+        let encoder = AnsiEncoder::new(ColorDepth::Monochrome);
+        let cells = [cell('#', RED, Some(BLUE), Attributes::NONE), cell('#', RED, None, Attributes::NONE)];
+        assert_eq!(encode(&encoder, &cells), "\x1b[48;5;21m#\x1b[49m#\n");
+    }
+
+    #[test]
+    fn rows_are_reset_only_if_something_was_set() {
+        let encoder = AnsiEncoder::new(ColorDepth::Monochrome);
+        let mut grid = Grid::new(2, 2);
+        grid.set(0, 0, cell('a', RED, None, Attributes::BOLD));
+        grid.set(1, 0, cell(' ', RED, None, Attributes::BOLD));
+        grid.set(0, 1, cell('b', RED, None, Attributes::BOLD));
+        assert_eq!(encoder.encode(&grid).unwrap(), b"\x1b[1ma \x1b[0m\n\x1b[1mb\x1b[22m \n");
+    }
+
+    #[test]
+    fn tolerance_skips_small_changes() {
+        let cells = [
+            cell('#', Rgba([100, 100, 100, 255]), None, Attributes::NONE),
+            cell('#', Rgba([108, 92, 100, 255]), None, Attributes::NONE),
+            cell('#', Rgba([109, 100, 100, 255]), None, Attributes::NONE),
+        ];
+        let encoder = AnsiEncoder::new(ColorDepth::TrueColor).with_tolerance(8);
+        assert_eq!(encode(&encoder, &cells), "\x1b[38;2;100;100;100m##\x1b[38;2;109;100;100m#\x1b[0m\n");
+
+        let encoder = AnsiEncoder::new(ColorDepth::TrueColor);
+        assert_eq!(encode(&encoder, &cells), "\x1b[38;2;100;100;100m#\x1b[38;2;108;92;100m#\x1b[38;2;109;100;100m#\x1b[0m\n");
+    }
+
+    #[test]
+    fn output_is_smaller_than_setting_every_cell() {
+        let image = image::open(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/mandelbrots.png")).unwrap();
+        let options = RenderOptions::builder().mode("shapes").width(100).color_depth(ColorDepth::Ansi256).build();
+        let grid = render(&image, &options).unwrap();
+
+        // Like the encoder used to do, a foreground color for every cell.
+        let every_cell: usize = grid.cells().iter()
+            .map(|cell| format!("\x1b[38;5;{}m{}", color_to_code(cell.fg), cell.glyph).len())
+            .sum::<usize>() + grid.height() as usize;
+        let encoded = AnsiEncoder::new(ColorDepth::Ansi256).encode(&grid).unwrap().len();
+        assert!(encoded * 2 < every_cell, "{encoded} bytes instead of {every_cell}");
+
+        let options = RenderOptions { color_depth: ColorDepth::TrueColor, ..options };
+        let grid = render(&image, &options).unwrap();
+        let exact = AnsiEncoder::new(ColorDepth::TrueColor).encode(&grid).unwrap().len();
+        let tolerant = AnsiEncoder::new(ColorDepth::TrueColor).with_tolerance(16).encode(&grid).unwrap().len();
+        assert!(tolerant < exact, "{tolerant} bytes with tolerance, {exact} without");
+    }
+}
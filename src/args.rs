@@ -0,0 +1,398 @@
+use std::{fs::File, io::{self, BufRead, BufReader, Cursor, IsTerminal, Read}, path::{Path, PathBuf}, time::Duration};
+
+use clap::{Parser, ValueEnum};
+use image::Delay;
+use uniart::{image_file::ImageFile, raw_stream::RawFormat, y4m_decoder::{self, Y4mFrames}, AnimationEncoder, AnsEncoder, AnsiEncoder, CastEncoder, ColorDepth, DiscordEncoder, Encoder, GifEncoder, HtmlEncoder, JsonEncoder, MircEncoder, Mode, PlainEncoder, PngEncoder, Rasterizer, Registry, RenderOptions, Result, SvgEncoder, TtyrecEncoder, UniartError};
+#[cfg(feature = "msgpack")]
+use uniart::MsgpackEncoder;
+
+/// Output formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Text with ansi escape sequences, for terminals.
+    Ansi,
+    /// Only the characters, without any escape sequences.
+    Text,
+    /// HTML document.
+    Html,
+    /// SVG image.
+    Svg,
+    /// PNG image, drawn with the built-in font.
+    Png,
+    /// GIF image, drawn with the built-in font. Animated GIFs stay animated.
+    Gif,
+    /// asciinema v2 recording of the animation.
+    Cast,
+    /// ttyrec recording of the animation.
+    Ttyrec,
+    /// ANSI art file in code page 437 with a SAUCE record, for BBS art viewers.
+    Ans,
+    /// Text with mIRC color codes, for IRC.
+    Mirc,
+    /// Text in an ansi code block with the colors Discord supports.
+    Discord,
+    /// The cells with their glyphs and colors as JSON.
+    Json,
+    /// The cells with their glyphs and colors as MessagePack.
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+}
+
+impl Format {
+    /// Guess the format from the extension of the output file.
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "txt" => Some(Format::Text),
+            "html" | "htm" => Some(Format::Html),
+            "svg" => Some(Format::Svg),
+            "png" => Some(Format::Png),
+            "gif" => Some(Format::Gif),
+            "cast" => Some(Format::Cast),
+            "ttyrec" | "tty" => Some(Format::Ttyrec),
+            "ans" => Some(Format::Ans),
+            "irc" => Some(Format::Mirc),
+            "json" => Some(Format::Json),
+            #[cfg(feature = "msgpack")]
+            "msgpack" | "mpk" => Some(Format::Msgpack),
+            _ => None,
+        }
+    }
+}
+
+/// Frame rate of image sequences, and of raw streams written to files, without `--fps`.
+const DEFAULT_FPS: f32 = 25.0;
+
+/// Time between frames at the frame rate. Fails if the frame rate is so low that the time can't be represented.
+fn delay_at(fps: f32) -> Result<Duration> {
+    Duration::try_from_secs_f64(1.0 / f64::from(fps)).map_err(|_| UniartError::InvalidOptions(format!("fps {fps} is too low")))
+}
+
+/// CLI app to display images in the terminal in different ways
+#[derive(Parser)]
+#[command(version = "1.0", author = "Gabriel Myszkier <myszkier.gabriel@gmail.com>", about = "Converts images to ascii art")]
+#[group()]
+pub struct Args {
+    /// Path to the image file, or - to read it from stdin
+    ///
+    /// Numbered images play as an animation when given as a printf-style or quoted glob pattern, e.g. frame_%04d.png or 'frame_*.png'.
+    pub image: String,
+
+    /// Reads the input as a stream of raw frames of this size and layout, e.g. 640x480:rgba.
+    ///
+    /// Every frame is shown as soon as it arrives, so the input can be a FIFO or the stdout of a running program.
+    #[arg(long, value_name = "WxH:LAYOUT", help_heading = "Input")]
+    pub raw: Option<RawFormat>,
+
+    /// Frame rate of raw streams and image sequences.
+    ///
+    /// Without it, raw frames are shown as fast as they arrive and written to files at 25 fps, and image sequences play at 25 fps.
+    #[arg(long, help_heading = "Input")]
+    pub fps: Option<f32>,
+
+    /// Mode. (one of: luminance, pixels, double-pixels, braille, edges, shapes, sixel, kitty, iterm)
+    ///
+    /// Can use only the first letter of the mode as a shortcut.
+    /// The sixel, kitty and iterm modes draw the image with pixels, in terminals that support these graphics protocols.
+    #[arg(short, long, default_value_t = String::from("shapes"), help_heading = "Output customization")]
+    pub mode: String,
+    
+    /// Sets the width of the output. If set to 0 it will fill the terminal window.
+    /// 
+    /// If the terminal size cannot be determined, 100 will be used.
+    #[arg(short, long, default_value_t = 0, help_heading = "Output customization")]
+    pub width: u32,
+
+    /// Sets the aspect ratio of the terminal font.
+    #[arg(short, long, default_value_t = 2.0, help_heading = "Output customization")]
+    pub aspect: f32,
+
+    /// Sets the character palette to use. Works in shapes mode.
+    /// 
+    /// Must be subset of [SPACE], A-Z, a-z, 0-9, !"#$%&'()*+,-./:;<=>?@[\]^_`{|}~ (ASCII 32-127)
+    #[arg(short, long, help_heading = "Output customization")]
+    pub palette: Option<String>,
+
+    /// Outputs the image in color.
+    /// 
+    /// Ansi escape sequences are not supported by all terminals, especially on older Windows versions.
+    #[arg(short, long, help_heading = "Color options")]
+    pub colors: bool,
+
+    /// Uses truecolor escape sequences. (only works in some terminals)
+    #[arg(short, long, help_heading = "Color options")]
+    pub truecolor: bool,
+
+    /// Sets the background color to black.
+    #[arg(short, long, help_heading = "Color options")]
+    pub background: bool,
+
+    /// Skips truecolor changes of at most this much in every channel. (0-255)
+    ///
+    /// Makes the output smaller at the cost of color accuracy, e.g. for saving or playback over SSH.
+    #[arg(long, default_value_t = 0, help_heading = "Color options")]
+    pub color_tolerance: u8,
+
+    /// Inverts the image brightness. (useful in white-background terminals)
+    #[arg(short, long, help_heading = "Image manipulation")]
+    pub invert: bool,
+
+    /// Switch the quality of output. (only works for luminance, edges and shapes modes)
+    /// 
+    /// For luminance and edges modes it will use a larger palette of characters,
+    /// which will potentially result in a better looking output.
+    /// For shapes mode it will reduce the quality of character choice and improve performance.
+    #[arg(short, long, help_heading = "Image manipulation")]
+    pub quality: bool,
+
+    /// Uses linear filter instead of nearest neighbor when scaling the image. 
+    /// 
+    /// Results in cleaner but less crisp output.
+    #[arg(short, long, help_heading = "Image manipulation")]
+    pub filter: bool,
+
+    /// Writes the output to a file instead of the terminal.
+    ///
+    /// Animations are only played in the terminal. The gif, cast, ttyrec and json formats keep all frames, other formats get the first one.
+    #[arg(short, long, help_heading = "Export")]
+    pub output: Option<PathBuf>,
+
+    /// Output format. Guessed from the extension of the output file if not given.
+    ///
+    /// Without an output file, text is written when stdout is not a terminal and no colors or background are requested.
+    #[arg(short = 'F', long, value_enum, help_heading = "Export")]
+    pub format: Option<Format>,
+
+    /// Removes whitespace at the end of lines in text output.
+    #[arg(long, help_heading = "Export")]
+    pub trim: bool,
+
+    /// Uses CSS classes instead of inline styles in HTML output.
+    #[arg(long, help_heading = "Export")]
+    pub css_classes: bool,
+
+    /// Font family of the text in SVG output.
+    #[arg(long, default_value_t = String::from("monospace"), help_heading = "Export")]
+    pub font_family: String,
+
+    /// Width of a character in pixels in SVG output. The height is derived from the aspect ratio.
+    #[arg(long, default_value_t = 8.0, help_heading = "Export")]
+    pub cell_width: f32,
+
+    /// Title of the art in the SAUCE record of ANS output.
+    #[arg(long, default_value_t = String::new(), hide_default_value = true, help_heading = "Export")]
+    pub title: String,
+
+    /// Author of the art in the SAUCE record of ANS output.
+    #[arg(long, default_value_t = String::new(), hide_default_value = true, help_heading = "Export")]
+    pub author: String,
+
+
+    // Not initialized by clap, filled later.
+    #[clap(skip)]
+    pub options: RenderOptions,
+
+    #[clap(skip)]
+    pub image_file: Option<ImageFile>,
+
+    /// A YUV4MPEG2 video, read while it's shown instead of being decoded up front.
+    #[clap(skip)]
+    pub video: Option<Y4mFrames<Box<dyn BufRead>>>,
+
+    #[clap(skip)]
+    pub configured_mode: Option<Mode>,
+}
+
+impl Args {
+    /// Validate the arguments, open the image and compute the output dimensions.
+    pub fn normalize(&mut self) -> Result<()> {
+        let registry = Registry::default();
+
+        // If the mode is not valid, try to find a mode that starts with the given string.
+        let Some((mode, _)) = registry.find(&self.mode) else {
+            return Err(UniartError::InvalidMode(self.mode.clone()));
+        };
+        let mode = mode.to_string();
+
+        if !(self.cell_width.is_finite() && self.cell_width > 0.0) {
+            return Err(UniartError::InvalidOptions(format!("cell width must be a positive number, got {}", self.cell_width)));
+        }
+        if let Some(fps) = self.fps {
+            if !(fps.is_finite() && fps > 0.0) {
+                return Err(UniartError::InvalidOptions(format!("fps must be a positive number, got {fps}")));
+            }
+            delay_at(fps)?;
+        }
+
+        // ANSI art is made for the 80 columns of its viewers.
+        let ans = self.format.or_else(|| self.output.as_deref().and_then(Format::from_path)) == Some(Format::Ans);
+        let width = match self.width {
+            0 if ans => AnsEncoder::MAX_WIDTH,
+            width if ans && width > AnsEncoder::MAX_WIDTH => {
+                return Err(UniartError::InvalidOptions(format!("ANS output is at most {} columns wide, got {width}", AnsEncoder::MAX_WIDTH)));
+            },
+            width => width,
+        };
+
+        self.options = RenderOptions::builder()
+            .mode(mode)
+            .width(width)
+            .aspect(self.aspect)
+            .color_depth(self.color_depth())
+            .background(self.background)
+            .invert(self.invert)
+            .quality(self.quality)
+            .filter(self.filter)
+            .build();
+        self.options.palette = self.palette.clone();
+
+        let mode = registry.configure(&mut self.options)?;
+        if let Mode::Graphics(_) = mode {
+            // Graphics are drawn straight to the terminal, they can't be converted to other formats.
+            let format = self.format.or_else(|| self.output.as_deref().and_then(Format::from_path));
+            if format.is_some_and(|format| format != Format::Ansi) {
+                return Err(UniartError::InvalidOptions(format!("the {} mode can only be written as terminal output", self.options.mode)));
+            }
+        }
+        self.configured_mode = Some(mode);
+
+        // Raw streams are read while they are shown, their size is known up front.
+        if let Some(raw) = self.raw {
+            (self.options.width, self.options.height) = self.options.dimensions_for((raw.width, raw.height));
+            return Ok(());
+        }
+
+        // Open image file. Only the header of a video is read here.
+        let image_file = if self.image == "-" {
+            let mut stdin = io::stdin().lock();
+            let mut start = Vec::new();
+            stdin.by_ref().take(y4m_decoder::MAGIC.len() as u64).read_to_end(&mut start)?;
+            let mut start = Cursor::new(start);
+            if y4m_decoder::is_y4m(&mut start)? {
+                return self.open_video(Box::new(start.chain(stdin)));
+            }
+            ImageFile::read(start.chain(stdin))?
+        }
+        else if ImageFile::is_sequence(&self.image) {
+            let delay = delay_at(self.fps.unwrap_or(DEFAULT_FPS))?;
+            ImageFile::open_sequence(&self.image, Delay::from_saturating_duration(delay))?
+        }
+        else {
+            let mut reader = BufReader::new(File::open(&self.image)?);
+            if y4m_decoder::is_y4m(&mut reader)? {
+                return self.open_video(Box::new(reader));
+            }
+            ImageFile::decode(reader)?
+        };
+
+        // Calculate the dimensions of the image in characters.
+        (self.options.width, self.options.height) = self.options.dimensions_for(image_file.dimensions());
+        self.image_file = Some(image_file);
+
+        Ok(())
+    }
+
+    /// Read the header of a video and compute the output dimensions from it.
+    fn open_video(&mut self, reader: Box<dyn BufRead>) -> Result<()> {
+        let video = Y4mFrames::new(reader)?;
+        (self.options.width, self.options.height) = self.options.dimensions_for(video.dimensions());
+        self.video = Some(video);
+        Ok(())
+    }
+
+    /// Open the input of a raw stream, stdin or a file like a FIFO.
+    pub fn raw_input(&self) -> Result<Box<dyn Read>> {
+        if self.image == "-" {
+            Ok(Box::new(io::stdin().lock()))
+        }
+        else {
+            Ok(Box::new(File::open(&self.image)?))
+        }
+    }
+
+    /// Time between the frames of a raw stream according to `--fps`.
+    ///
+    /// Without it, frames are shown in the terminal as they arrive, and files get the default frame rate.
+    pub fn frame_delay(&self) -> Result<Duration> {
+        match self.fps {
+            Some(fps) => delay_at(fps),
+            None if self.plays_in_terminal() => Ok(Duration::ZERO),
+            None => delay_at(DEFAULT_FPS),
+        }
+    }
+
+    /// Whether animations are played in the terminal instead of being written at once.
+    pub fn plays_in_terminal(&self) -> bool {
+        let graphics = matches!(self.configured_mode, Some(Mode::Graphics(_)));
+        self.output.is_none() && (graphics || self.format() == Format::Ansi)
+    }
+
+    /// The mode created by `normalize`.
+    pub fn configured_mode(&self) -> Result<&Mode> {
+        self.configured_mode.as_ref().ok_or_else(|| UniartError::InvalidOptions(String::from("arguments were not normalized")))
+    }
+
+    /// Color depth of the output according to the color flags.
+    fn color_depth(&self) -> ColorDepth {
+        if self.truecolor {
+            ColorDepth::TrueColor
+        }
+        else if self.colors {
+            ColorDepth::Ansi256
+        }
+        else {
+            ColorDepth::Monochrome
+        }
+    }
+
+    /// Output format, either given or guessed from the output file.
+    pub fn format(&self) -> Format {
+        self.format
+            .or_else(|| self.output.as_deref().and_then(Format::from_path))
+            .unwrap_or_else(|| {
+                let plain = self.options.color_depth == ColorDepth::Monochrome && !self.options.background;
+                if self.output.is_none() && plain && !io::stdout().is_terminal() {Format::Text} else {Format::Ansi}
+            })
+    }
+
+    /// Encoder of the selected output format.
+    pub fn encoder(&self) -> Result<Box<dyn Encoder>> {
+        let depth = self.options.color_depth;
+        Ok(match self.format() {
+            Format::Ansi => Box::new(self.ansi_encoder()),
+            Format::Text => Box::new(PlainEncoder::new().with_trim(self.trim)),
+            Format::Html => Box::new(HtmlEncoder::new(depth).with_classes(self.css_classes).with_dark(!self.invert)),
+            Format::Svg => Box::new(SvgEncoder::new(depth, self.cell_width, self.aspect).with_font_family(&self.font_family).with_dark(!self.invert)),
+            Format::Png => Box::new(PngEncoder::new(self.rasterizer()?)),
+            Format::Gif => Box::new(GifEncoder::new(self.rasterizer()?)),
+            Format::Cast => Box::new(CastEncoder::new(self.ansi_encoder())),
+            Format::Ttyrec => Box::new(TtyrecEncoder::new(self.ansi_encoder())),
+            Format::Ans => Box::new(AnsEncoder::new(depth).with_title(&self.title).with_author(&self.author)),
+            Format::Mirc => Box::new(MircEncoder::new(depth)),
+            Format::Discord => Box::new(DiscordEncoder::new(depth)),
+            Format::Json => Box::new(JsonEncoder::new(&self.options.mode)),
+            #[cfg(feature = "msgpack")]
+            Format::Msgpack => Box::new(MsgpackEncoder::new(&self.options.mode)),
+        })
+    }
+
+    /// Encoder of the selected output format if it can hold all frames of an animation.
+    pub fn animation_encoder(&self) -> Result<Option<Box<dyn AnimationEncoder>>> {
+        Ok(match self.format() {
+            Format::Gif => Some(Box::new(GifEncoder::new(self.rasterizer()?))),
+            Format::Cast => Some(Box::new(CastEncoder::new(self.ansi_encoder()))),
+            Format::Ttyrec => Some(Box::new(TtyrecEncoder::new(self.ansi_encoder()))),
+            Format::Json => Some(Box::new(JsonEncoder::new(&self.options.mode))),
+            #[cfg(feature = "msgpack")]
+            Format::Msgpack => Some(Box::new(MsgpackEncoder::new(&self.options.mode))),
+            _ => None,
+        })
+    }
+
+    fn ansi_encoder(&self) -> AnsiEncoder {
+        AnsiEncoder::new(self.options.color_depth).with_tolerance(self.color_tolerance)
+    }
+
+    fn rasterizer(&self) -> Result<Rasterizer> {
+        Ok(Rasterizer::new(self.options.color_depth, false)?.with_dark(!self.invert))
+    }
+}
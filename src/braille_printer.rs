@@ -1,48 +1,60 @@
-use image::{imageops::{dither, BiLevel}, DynamicImage, GenericImageView};
+use image::{imageops::{dither, BiLevel, FilterType}, DynamicImage, GenericImageView, Rgba};
 
-use crate::{colors::{reset_color, set_black_background, set_color_full_brightness}, Args};
+use crate::{colors::{background, full_brightness}, grid::{Cell, Grid}, options::RenderOptions, renderer::Renderer};
 
 /// Dot weights for Braille characters in unicode. 
-const WEIGHTS: [[u8; 2]; 4] = [
+pub const WEIGHTS: [[u8; 2]; 4] = [
     [0x1,  0x8], 
     [0x2,  0x10],
     [0x4,  0x20], 
     [0x40, 0x80]
 ];
 
-pub fn print_braille(args: &Args, image: &DynamicImage) {
-    // one pixel per symbol
-    let colors = image.resize_exact( 
-        args.width, 
-        args.height, 
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
-
-    // 2x4 pixels per symbol (braille grid)
-    let image = image.resize_exact( 
-        args.width*2, 
-        args.height*4, 
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
-    let mut image = image.to_luma8();
-    dither(&mut image, &BiLevel);
-
-    for y in (0..image.height()).step_by(4) {
-        set_black_background(args);
-        for x in (0..image.width()).step_by(2) {
-            let mut braille_code = 0;
-            for dy in 0..4 {
-                for dx in 0..2 {
-                    let [luminance] = image.get_pixel(x+dx, y+dy).0;
-                    let mut bit = (luminance > 128) as u8;
-                    if args.invert { bit = 1 - bit; }
-                    braille_code += bit * WEIGHTS[dy as usize][dx as usize];
+/// Uses the dots of braille characters as 2x4 pixels per character.
+pub struct BrailleRenderer {
+    invert: bool,
+    filter: FilterType,
+    background: Option<Rgba<u8>>,
+}
+
+impl BrailleRenderer {
+    pub fn new(options: &RenderOptions) -> Self {
+        BrailleRenderer {
+            invert: options.invert,
+            filter: options.filter_type(),
+            background: background(options),
+        }
+    }
+}
+
+impl Renderer for BrailleRenderer {
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid {
+        // one pixel per symbol
+        let colors = image.resize_exact(width, height, self.filter);
+
+        // 2x4 pixels per symbol (braille grid)
+        let image = image.resize_exact(width*2, height*4, self.filter);
+        let mut image = image.to_luma8();
+        dither(&mut image, &BiLevel);
+
+        let mut grid = Grid::new(width, height);
+        for y in (0..image.height()).step_by(4) {
+            for x in (0..image.width()).step_by(2) {
+                let mut braille_code = 0;
+                for dy in 0..4 {
+                    for dx in 0..2 {
+                        let [luminance] = image.get_pixel(x+dx, y+dy).0;
+                        let mut bit = (luminance > 128) as u8;
+                        if self.invert { bit = 1 - bit; }
+                        braille_code += bit * WEIGHTS[dy as usize][dx as usize];
+                    }
                 }
+                let glyph = std::char::from_u32(0x2800 + braille_code as u32).unwrap();
+                let fg = full_brightness(colors.get_pixel(x/2, y/4), self.invert);
+                grid.set(x/2, y/4, Cell::new(glyph, fg, self.background));
             }
-            set_color_full_brightness(colors.get_pixel(x/2, y/4), args);
-            print!("{}", std::char::from_u32(0x2800 + braille_code as u32).unwrap());
         }
-        reset_color();
-        println!();
+
+        grid
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,43 @@
+use std::{io, time::Duration};
+
+use serde::Serialize;
+
+use crate::{ansi_encoder::AnsiEncoder, encoder::AnimationEncoder, grid::Frame};
+
+/// Header line of an asciinema v2 recording.
+#[derive(Serialize)]
+struct Header {
+    version: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Emits an asciinema v2 recording (`.cast`) that replays the frames with their delays.
+///
+/// The terminal size of the recording is the size of the first frame. A single grid is recorded as a single frame.
+pub struct CastEncoder {
+    pub ansi: AnsiEncoder,
+}
+
+impl CastEncoder {
+    pub fn new(ansi: AnsiEncoder) -> Self {
+        CastEncoder { ansi }
+    }
+}
+
+/// Append an output event at the given time since the start of the recording.
+fn push_event(buf: &mut Vec<u8>, time: Duration, data: &[u8]) -> io::Result<()> {
+    serde_json::to_writer(&mut *buf, &(time.as_secs_f64(), "o", String::from_utf8_lossy(data)))?;
+    buf.push(b'\n');
+    Ok(())
+}
+
+impl AnimationEncoder for CastEncoder {
+    fn encode_frames_into(&self, frames: &[Frame], buf: &mut Vec<u8>) -> io::Result<()> {
+        let (width, height) = frames.first().map_or((0, 0), |frame| (frame.grid.width(), frame.grid.height()));
+        serde_json::to_writer(&mut *buf, &Header { version: 2, width, height })?;
+        buf.push(b'\n');
+
+        self.ansi.encode_recording(frames, |time, data| push_event(buf, time, data))
+    }
+}
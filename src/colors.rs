@@ -1,44 +1,82 @@
-use image::Rgba;
+use image::{Rgb, Rgba};
 
-use crate::Args;
+use crate::{encoder::ColorDepth, grid::Cell, options::RenderOptions};
+
+/// Palette codes of the foreground and background of a cell. `None` is the default color of the output.
+pub type PaletteCodes = (Option<u8>, Option<u8>);
+
+/// Channel values of the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Color used by the `--background` flag.
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
 
 /// Convert an RGBA color to a 256-color terminal code.
-fn color_to_code(color: Rgba<u8>) -> u8 {
+pub fn color_to_code(color: Rgba<u8>) -> u8 {
     let [r, g, b, a] = color.0;
     // multiply by alpha and map to 0-5 integer
     let r = (r as f32 * a as f32 / 255.0 / 255.0 * 5.0).round() as u8;
     let g = (g as f32 * a as f32 / 255.0 / 255.0 * 5.0).round() as u8;
     let b = (b as f32 * a as f32 / 255.0 / 255.0 * 5.0).round() as u8;
-    
+
     16 + 36*r + 6*g + b
 }
 
-/// Reset the terminal color to default.
-pub fn reset_color() {
-    print!("\x1b[0m")
+/// Index of the palette color closest to the given color, which is premultiplied by alpha like in [`color_to_code`].
+pub fn closest_in_palette(color: Rgba<u8>, palette: &[[u8; 3]]) -> usize {
+    let [r, g, b, a] = color.0;
+    let premultiplied = [r, g, b].map(|c| (c as u32 * a as u32 / 255) as i32);
+    let distance = |c: &[u8; 3]| (0..3).map(|i| (c[i] as i32 - premultiplied[i]).pow(2)).sum::<i32>();
+    (0..palette.len()).min_by_key(|&i| distance(&palette[i])).unwrap_or(0)
+}
+
+/// Palette codes of a cell, for formats that only emit a color change when the codes differ from those of the previous cell.
+///
+/// Monochrome keeps the default foreground. Spaces keep the previous one, as it isn't visible, which saves a change.
+pub fn palette_codes(cell: &Cell, depth: ColorDepth, previous: PaletteCodes, fg_code: impl Fn(Rgba<u8>) -> u8, bg_code: impl Fn(Rgba<u8>) -> u8) -> PaletteCodes {
+    let fg = match depth {
+        ColorDepth::Monochrome => None,
+        _ if cell.glyph == ' ' => previous.0,
+        _ => Some(fg_code(cell.fg)),
+    };
+    (fg, cell.bg.map(bg_code))
 }
 
-/// Set the terminal color to a given color according to the arguments.
-pub fn set_color(col: Rgba<u8>, args: &Args) {
-    if args.truecolor {
-        let [r, g, b, _] = col.0;
-        print!("\x1b[38;2;{r};{g};{b}m")
-    } 
-    else if args.colors {
-        let code = color_to_code(col);
-        print!("\x1b[38;5;{code}m")
+/// Convert a 256-color terminal code from the color cube back to RGB.
+pub fn code_to_color(code: u8) -> Rgb<u8> {
+    let code = code.max(16) - 16;
+    Rgb([CUBE_LEVELS[(code / 36 % 6) as usize], CUBE_LEVELS[(code / 6 % 6) as usize], CUBE_LEVELS[(code % 6) as usize]])
+}
+
+/// The color a terminal displays for the given color at the given color depth.
+///
+/// Monochrome is treated like 256 colors, as that's how the background is emitted.
+pub fn displayed_color(col: Rgba<u8>, depth: ColorDepth) -> Rgb<u8> {
+    match depth {
+        ColorDepth::TrueColor => Rgb([col[0], col[1], col[2]]),
+        ColorDepth::Ansi256 | ColorDepth::Monochrome => code_to_color(color_to_code(col)),
     }
 }
 
-/// Set the terminal color to a given color according to the arguments, with full brightness. Doesn't work with invert.
-pub fn set_color_full_brightness(col: Rgba<u8>, args: &Args) {
-    if !args.colors && !args.truecolor { return; }
-    if args.invert {
+/// Default text and background colors of the page, for cells that don't set their own.
+///
+/// A dark page has light text, like most terminals. Inverted images are meant for a light page with dark text.
+pub fn page_colors(dark: bool) -> (Rgb<u8>, Rgb<u8>) {
+    if dark {(Rgb([255, 255, 255]), Rgb([0, 0, 0]))} else {(Rgb([0, 0, 0]), Rgb([255, 255, 255]))}
+}
+
+/// Format a color as a `#rrggbb` hex code.
+pub fn to_hex(col: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", col[0], col[1], col[2])
+}
+
+/// Scale a color to full brightness. Doesn't work with invert, so the color is returned unchanged in that case.
+pub fn full_brightness(col: Rgba<u8>, invert: bool) -> Rgba<u8> {
+    if invert {
         // Brightness recompensation doesn't work for on light backgrounds.
-        set_color(col, args);
-        return;
+        return col;
     }
-    
+
     // Scale the color as much as possible without clipping.
     let (mut r, mut g, mut b) = (col[0] as f32/255.0, col[1] as f32/255.0, col[2] as f32/255.0);
     let max = r.max(g).max(b);
@@ -46,30 +84,10 @@ pub fn set_color_full_brightness(col: Rgba<u8>, args: &Args) {
     g /= max;
     b /= max;
     let (r, g, b) = ((r*255.0) as u8, (g*255.0) as u8, (b*255.0) as u8);
-    let col = Rgba::from([r, g, b, col.0[3]]);
-    set_color(col, args)
+    Rgba::from([r, g, b, col.0[3]])
 }
 
-/// Set the terminal color to a given foreground and background color according to the arguments.
-pub fn set_color_bg(fg: Rgba<u8>, bg: Rgba<u8>, args: &Args) {
-    if !args.colors && !args.truecolor { return; }
-    
-    if args.truecolor {
-        let [rf, gf, bf, _] = fg.0;
-        let [rb, gb, bb, _] = bg.0;
-
-        print!("\x1b[48;2;{rb};{gb};{bb}m\x1b[38;2;{rf};{gf};{bf}m")
-    }
-    else {
-        let fg_code = color_to_code(fg);
-        let bg_code = color_to_code(bg);
-        print!("\x1b[38;5;{fg_code}m\x1b[48;5;{bg_code}m")
-    }
+/// Background color of the cells according to the background option.
+pub fn background(options: &RenderOptions) -> Option<Rgba<u8>> {
+    if options.background { Some(BLACK) } else { None }
 }
-
-/// Set the terminal background color to black if the background flag is set.
-pub fn set_black_background(args: &Args) {
-    if args.background {
-        print!("\x1b[48;5;0m");
-    }
-}
\ No newline at end of file
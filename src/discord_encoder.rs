@@ -0,0 +1,106 @@
+use std::io;
+
+use crate::{colors::{closest_in_palette, palette_codes, PaletteCodes}, encoder::{push_char, push_u8, ColorDepth, Encoder}, grid::Grid};
+
+/// Foreground colors of Discord's ansi code blocks, for codes 30 to 37.
+const FG_PALETTE: [[u8; 3]; 8] = [
+    [0x4f, 0x54, 0x5c], [0xdc, 0x32, 0x2f], [0x85, 0x99, 0x00], [0xb5, 0x89, 0x00],
+    [0x26, 0x8b, 0xd2], [0xd3, 0x36, 0x82], [0x2a, 0xa1, 0x98], [0xff, 0xff, 0xff],
+];
+
+/// Background colors of Discord's ansi code blocks, for codes 40 to 47.
+const BG_PALETTE: [[u8; 3]; 8] = [
+    [0x00, 0x2b, 0x36], [0xcb, 0x4b, 0x16], [0x58, 0x6e, 0x75], [0x65, 0x7b, 0x83],
+    [0x83, 0x94, 0x96], [0x6c, 0x71, 0xc4], [0x93, 0xa1, 0xa1], [0xfd, 0xf6, 0xe3],
+];
+
+/// Emits the grid in an ```` ```ansi ```` code block, which Discord displays in color.
+///
+/// Discord only understands resets and the 8 basic foreground and background colors, which it displays in its own palette.
+/// Messages are limited to 2000 characters, so colored art has to be small.
+pub struct DiscordEncoder {
+    pub depth: ColorDepth,
+}
+
+impl DiscordEncoder {
+    pub fn new(depth: ColorDepth) -> Self {
+        DiscordEncoder { depth }
+    }
+}
+
+impl Encoder for DiscordEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.extend_from_slice(b"```ansi\n");
+        let fg_code = |col| closest_in_palette(col, &FG_PALETTE) as u8;
+        let bg_code = |col| closest_in_palette(col, &BG_PALETTE) as u8;
+        for row in grid.rows() {
+            // Colors as indices into the palettes. `None` is the default.
+            let mut state: PaletteCodes = (None, None);
+            let mut backticks = 0;
+            for cell in row {
+                let (fg, bg) = palette_codes(cell, self.depth, state, fg_code, bg_code);
+
+                // Every change starts from a reset, as Discord doesn't know the codes of default colors.
+                if (fg, bg) != state {
+                    buf.extend_from_slice(b"\x1b[0");
+                    if let Some(fg) = fg {
+                        buf.extend_from_slice(b";3");
+                        push_u8(buf, fg);
+                    }
+                    if let Some(bg) = bg {
+                        buf.extend_from_slice(b";4");
+                        push_u8(buf, bg);
+                    }
+                    buf.push(b'm');
+                    state = (fg, bg);
+                }
+
+                // Three backticks in a row would close the code block.
+                backticks = if cell.glyph == '`' {backticks + 1} else {0};
+                if backticks == 3 {
+                    buf.push(b'\'');
+                    backticks = 0;
+                }
+                else {
+                    push_char(buf, cell.glyph);
+                }
+            }
+            if state != (None, None) {
+                buf.extend_from_slice(b"\x1b[0m");
+            }
+            buf.push(b'\n');
+        }
+        buf.extend_from_slice(b"```\n");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use crate::grid::Cell;
+
+    use super::*;
+
+    fn encode(depth: ColorDepth, text: &str) -> String {
+        let mut grid = Grid::new(text.chars().count() as u32, 1);
+        for (x, glyph) in text.chars().enumerate() {
+            grid.set(x as u32, 0, Cell::new(glyph, Rgba([255, 0, 0, 255]), None));
+        }
+        let mut buf = Vec::new();
+        DiscordEncoder::new(depth).encode_into(&grid, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn backticks_cant_close_the_code_block() {
+        assert_eq!(encode(ColorDepth::Monochrome, "``````a```"), "```ansi\n``'``'a``'\n```\n");
+        assert_eq!(encode(ColorDepth::Monochrome, "``a``"), "```ansi\n``a``\n```\n");
+    }
+
+    #[test]
+    fn colors_are_escaped_only_when_changed() {
+        assert_eq!(encode(ColorDepth::Ansi256, "ab ````"), "```ansi\n\x1b[0;31mab ``'`\x1b[0m\n```\n");
+    }
+}
@@ -1,8 +1,8 @@
 use std::f32::consts::PI;
 
-use image::{DynamicImage, GenericImageView, ImageBuffer};
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageBuffer, Rgba};
 
-use crate::{colors::{reset_color, set_black_background, set_color_full_brightness}, luminance_printer::char_from_color, Args};
+use crate::{colors::{background, full_brightness}, grid::{Cell, Grid}, luminance_printer::char_from_color, options::RenderOptions, renderer::Renderer};
 
 const SOBEL_X: [i16; 9] = [
     -2, 0, 2,
@@ -44,39 +44,56 @@ pub fn convolve(image: &DynamicImage, kernel: &[i16; 9]) -> ImageBuffer<image::L
     })
 }
 
-pub fn print_edges(args: &Args, image: &DynamicImage) {
-    let image = image.resize_exact(
-        args.width, 
-        args.height, 
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
-    
-    let edges_x =  convolve(&image, &SOBEL_X);
-    let edges_y =  convolve(&image, &SOBEL_Y);
+/// Luminance characters with line characters along the detected edges.
+pub struct EdgesRenderer {
+    dense: bool,
+    invert: bool,
+    filter: FilterType,
+    background: Option<Rgba<u8>>,
+}
+
+impl EdgesRenderer {
+    pub fn new(options: &RenderOptions) -> Self {
+        EdgesRenderer {
+            dense: options.quality,
+            invert: options.invert,
+            filter: options.filter_type(),
+            background: background(options),
+        }
+    }
+}
 
-    for y in 0..image.height() {
-        set_black_background(args);
-        for x in 0..image.width() {
-            let p_x = edges_y.get_pixel(x, y).0[0] as f32;
-            let p_y = edges_x.get_pixel(x, y).0[0] as f32;
-            let p = (p_x*p_x + p_y*p_y).sqrt();
+impl Renderer for EdgesRenderer {
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid {
+        let image = image.resize_exact(width, height, self.filter);
 
-            let pixel  = image.get_pixel(x, y);
-            set_color_full_brightness(pixel, args);
-            
-            if p < 1000.0 { 
-                // print luma
-                print!("{}", char_from_color(pixel, args));
-            }
-            else { 
-                // print appropriate edge character
-                let mut angle = f32::atan2(p_y, p_x);
-                if angle < 0.0 { angle += PI; }
-                let i = ((angle + PI/8.0) / (PI/4.0)) as usize % 4;
-                print!("{}", EDGES[i]);
+        let edges_x =  convolve(&image, &SOBEL_X);
+        let edges_y =  convolve(&image, &SOBEL_Y);
+
+        let mut grid = Grid::new(image.width(), image.height());
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p_x = edges_y.get_pixel(x, y).0[0] as f32;
+                let p_y = edges_x.get_pixel(x, y).0[0] as f32;
+                let p = (p_x*p_x + p_y*p_y).sqrt();
+
+                let pixel  = image.get_pixel(x, y);
+
+                let glyph = if p < 1000.0 {
+                    // luma character
+                    char_from_color(pixel, self.invert, self.dense)
+                }
+                else {
+                    // appropriate edge character
+                    let mut angle = f32::atan2(p_y, p_x);
+                    if angle < 0.0 { angle += PI; }
+                    let i = ((angle + PI/8.0) / (PI/4.0)) as usize % 4;
+                    EDGES[i]
+                };
+                grid.set(x, y, Cell::new(glyph, full_brightness(pixel, self.invert), self.background));
             }
         }
-        reset_color();
-        println!();
+
+        grid
     }
-}
\ No newline at end of file
+}
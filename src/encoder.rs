@@ -0,0 +1,98 @@
+use std::{io::{self, Write}, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::grid::{Frame, Grid};
+
+/// Color capabilities of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorDepth {
+    /// Only the glyphs (and the background flag) are emitted.
+    Monochrome,
+    /// 256 ansi color codes.
+    Ansi256,
+    /// 24bit true color.
+    TrueColor,
+}
+
+/// Turns a rendered grid into an output format.
+pub trait Encoder {
+    /// Append the encoded grid to the buffer.
+    ///
+    /// Fails if the grid can't be represented in the format, e.g. when it is too large.
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Encode the grid into a new buffer.
+    fn encode(&self, grid: &Grid) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.encode_into(grid, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Encode the whole grid first and then write it to the sink at once.
+    fn write(&self, grid: &Grid, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(&self.encode(grid)?)?;
+        out.flush()
+    }
+}
+
+/// Turns a sequence of rendered grids into an animated output format.
+pub trait AnimationEncoder {
+    /// Append the encoded animation to the buffer.
+    ///
+    /// Fails if the frames can't be represented in the format, e.g. when they are too large.
+    fn encode_frames_into(&self, frames: &[Frame], buf: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Encode the whole animation first and then write it to the sink at once.
+    fn write_frames(&self, frames: &[Frame], out: &mut dyn Write) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.encode_frames_into(frames, &mut buf)?;
+        out.write_all(&buf)?;
+        out.flush()
+    }
+}
+
+/// Every animation can also be encoded from a single grid, as an animation of one frame without delay.
+impl<T: AnimationEncoder + ?Sized> Encoder for T {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        let frame = Frame { grid: grid.clone(), delay: Duration::ZERO };
+        self.encode_frames_into(std::slice::from_ref(&frame), buf)
+    }
+}
+
+/// Append a character to the buffer as UTF-8.
+pub fn push_char(buf: &mut Vec<u8>, c: char) {
+    let mut bytes = [0; 4];
+    buf.extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+}
+
+/// Append a character, escaping the characters that have a meaning in XML and HTML.
+pub fn push_xml_escaped(buf: &mut Vec<u8>, c: char) {
+    match c {
+        '<' => buf.extend_from_slice(b"&lt;"),
+        '>' => buf.extend_from_slice(b"&gt;"),
+        '&' => buf.extend_from_slice(b"&amp;"),
+        '"' => buf.extend_from_slice(b"&quot;"),
+        c => push_char(buf, c),
+    }
+}
+
+/// Append a number in decimal, without going through the formatting machinery.
+pub fn push_u8(buf: &mut Vec<u8>, n: u8) {
+    if n >= 100 {
+        buf.push(b'0' + n / 100);
+    }
+    if n >= 10 {
+        buf.push(b'0' + n / 10 % 10);
+    }
+    buf.push(b'0' + n % 10);
+}
+
+/// Append a larger number in decimal.
+pub fn push_u32(buf: &mut Vec<u8>, n: u32) {
+    if n >= 10 {
+        push_u32(buf, n / 10);
+    }
+    buf.push(b'0' + (n % 10) as u8);
+}
@@ -0,0 +1,84 @@
+use std::{fmt, io};
+
+use image::ImageError;
+
+/// Everything that can go wrong while rendering.
+#[derive(Debug)]
+pub enum UniartError {
+    /// The options are inconsistent or out of range.
+    InvalidOptions(String),
+    /// No mode matches the given name.
+    InvalidMode(String),
+    /// The palette contains a character that can't be rendered.
+    InvalidPalette(char),
+    /// The input couldn't be read.
+    Io(io::Error),
+    /// The input is not in a supported format.
+    UnsupportedFormat(String),
+    /// The input couldn't be decoded.
+    Decode(ImageError),
+    /// Writing to or querying the terminal failed.
+    Terminal(io::Error),
+    /// The output file couldn't be written.
+    Write(io::Error),
+}
+
+impl UniartError {
+    /// Process exit code for this kind of error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            UniartError::InvalidOptions(_) => 2,
+            UniartError::InvalidMode(_) => 3,
+            UniartError::InvalidPalette(_) => 4,
+            UniartError::Io(_) => 5,
+            UniartError::UnsupportedFormat(_) => 6,
+            UniartError::Decode(_) => 7,
+            UniartError::Terminal(_) => 8,
+            UniartError::Write(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for UniartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniartError::InvalidOptions(message) => write!(f, "Invalid options: {message}"),
+            UniartError::InvalidMode(mode) => write!(f, "Invalid mode: {mode}"),
+            UniartError::InvalidPalette(c) => write!(f, "Invalid character in the palette: {c}"),
+            UniartError::Io(e) => write!(f, "Failed to read the input: {e}"),
+            UniartError::UnsupportedFormat(message) => write!(f, "Unsupported format: {message}"),
+            UniartError::Decode(e) => write!(f, "Failed to decode the image: {e}"),
+            UniartError::Terminal(e) => write!(f, "Terminal error: {e}"),
+            UniartError::Write(e) => write!(f, "Failed to write the output: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for UniartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UniartError::Io(e) | UniartError::Terminal(e) | UniartError::Write(e) => Some(e),
+            UniartError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ImageError> for UniartError {
+    fn from(e: ImageError) -> Self {
+        match e {
+            ImageError::IoError(e) => UniartError::Io(e),
+            ImageError::Unsupported(e) => UniartError::UnsupportedFormat(e.to_string()),
+            e => UniartError::Decode(e),
+        }
+    }
+}
+
+impl From<io::Error> for UniartError {
+    /// Input errors. Terminal and output errors have to be wrapped in [`UniartError::Terminal`] or [`UniartError::Write`] explicitly.
+    fn from(e: io::Error) -> Self {
+        UniartError::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, UniartError>;
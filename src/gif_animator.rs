@@ -1,55 +1,104 @@
-use std::{io::Write, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::Instant};
+use std::{io::{self, Stdout, Write}, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::{Duration, Instant}};
 
-use crossterm::ExecutableCommand;
+use crossterm::{ExecutableCommand, QueueableCommand};
 
-use crate::{image_file::GifFrame, Args};
+use image::DynamicImage;
+use uniart::{image_file::GifFrame, Result, UniartError};
 
-pub fn animate_gif(args: &Args, frames: &Vec<GifFrame>) {
+use crate::args::Args;
+
+/// Appends the terminal output that draws a frame at the cursor. Fails if the frame can't be encoded.
+pub type Draw<'a> = dyn Fn(&DynamicImage, &mut Vec<u8>) -> io::Result<()> + 'a;
+
+/// Draws the next frame and returns how long to wait before showing it, or `None` at the end of the animation.
+type NextFrame<'a> = dyn FnMut(&mut Vec<u8>) -> Result<Option<Duration>> + 'a;
+
+/// Play the frames in a loop until the program is interrupted.
+pub fn animate_gif(args: &Args, frames: &[GifFrame], draw: &Draw) -> Result<()> {
+    if frames.is_empty() {
+        return Ok(());
+    }
+
+    let mut i = 0;
+    animate(args, &mut |buf| {
+        let frame = &frames[i];
+        draw(&frame.image, buf).map_err(UniartError::Write)?;
+        i = (i + 1) % frames.len();
+        Ok(Some(frame.dalay_as_duration()))
+    })
+}
+
+/// Play the frames once, each as soon as it arrives but at most one every `delay`.
+pub fn animate_stream<I>(args: &Args, mut frames: I, delay: Duration, draw: &Draw) -> Result<()>
+where I: Iterator<Item = Result<DynamicImage>> {
+    animate(args, &mut |buf| {
+        let Some(image) = frames.next().transpose()? else { return Ok(None) };
+        draw(&image, buf).map_err(UniartError::Write)?;
+        Ok(Some(delay))
+    })
+}
+
+fn animate(args: &Args, next_frame: &mut NextFrame) -> Result<()> {
     // Make sure the cursor is shown when the program exits.
     let running = Arc::new(AtomicBool::new(true));
     let r = Arc::clone(&running);
 
     ctrlc::set_handler(move || {
-        running.store(false, Ordering::SeqCst);
-        // Show cursor when exiting
+        // A stream may be waiting for a frame that never comes, so a second interrupt exits right away.
+        if !running.swap(false, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        // Show cursor when exiting. There is nowhere to report a failure to.
         let mut stdout = std::io::stdout();
-        stdout.execute(crossterm::cursor::Show).expect("Failed to show cursor.");
-        stdout.flush().unwrap();
-    }).expect("Failed to set Ctrl-C handler.");
+        let _ = stdout.execute(crossterm::cursor::Show);
+        let _ = stdout.flush();
+    }).map_err(|e| UniartError::Terminal(std::io::Error::other(e)))?;
 
     let mut stdout = std::io::stdout();
     // Hide cursor.
-    stdout.execute(crossterm::cursor::Hide).expect("Failed to hide cursor.");
+    stdout.execute(crossterm::cursor::Hide).map_err(UniartError::Terminal)?;
+
+    let result = play(args, next_frame, &r, &mut stdout);
 
+    // Show the cursor again, even if the animation failed.
+    stdout.execute(crossterm::cursor::Show).map_err(UniartError::Terminal)?;
+    stdout.flush().map_err(UniartError::Terminal)?;
+    result
+}
+
+fn play(args: &Args, next_frame: &mut NextFrame, running: &AtomicBool, stdout: &mut Stdout) -> Result<()> {
     // Prepare the space for the animation.
-    for _ in 0..args.height {
+    for _ in 0..args.options.height {
         println!();
     }
 
     // Return the cursor to the top of the canvas.
-    stdout.execute(crossterm::cursor::MoveUp(args.height as u16)).expect("Failed to restore cursor position.");
+    stdout.execute(crossterm::cursor::MoveUp(args.options.height as u16)).map_err(UniartError::Terminal)?;
 
     // Remember the start cursor position.
-    let origin = crossterm::cursor::position().expect("Failed to get cursor position.");
-    
+    let origin = crossterm::cursor::position().map_err(UniartError::Terminal)?;
+
     // Animate.
+    // Each frame is assembled here and written at once, so that it doesn't flicker.
+    let mut buf = Vec::new();
     let mut last_frame = Instant::now();
 
-    let mut i = 0;
-    while r.load(Ordering::SeqCst) {
-        let frame = &frames[i];
+    while running.load(Ordering::SeqCst) {
+        buf.clear();
+        buf.queue(crossterm::cursor::MoveTo(origin.0, origin.1)).map_err(UniartError::Terminal)?;
+        let Some(delay) = next_frame(&mut buf)? else {
+            // Leave the last frame on screen and continue below it.
+            let below = origin.1.saturating_add(args.options.height as u16);
+            stdout.execute(crossterm::cursor::MoveTo(0, below)).map_err(UniartError::Terminal)?;
+            break;
+        };
 
-        if last_frame.elapsed() < frame.dalay_as_duration() {
-            thread::sleep(frame.dalay_as_duration() - last_frame.elapsed());
-        }
+        thread::sleep(delay.saturating_sub(last_frame.elapsed()));
         last_frame = Instant::now();
-        
-        stdout.execute(crossterm::cursor::MoveTo(origin.0, origin.1)).expect("Failed to move cursor.");
-        (args.printer.unwrap())(args, &frame.image);
-        i = (i + 1) % frames.len();
 
+        let mut lock = stdout.lock();
+        lock.write_all(&buf).and_then(|_| lock.flush()).map_err(UniartError::Terminal)?;
     }
 
-    stdout.execute(crossterm::cursor::Show).expect("Failed to show cursor.");
-    std::io::stdout().flush().unwrap();
-}
\ No newline at end of file
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::io;
+
+use image::{codecs::gif::{self, Repeat}, Delay, DynamicImage};
+
+use crate::{encoder::AnimationEncoder, grid::Frame, rasterizer::Rasterizer};
+
+/// Emits an animated GIF of the frames as a terminal would show them, looping forever.
+///
+/// A single grid is encoded as a still GIF.
+pub struct GifEncoder {
+    pub rasterizer: Rasterizer,
+    /// Quality of the color quantization, from 1 (slowest, best) to 30 (fastest).
+    pub speed: i32,
+}
+
+impl GifEncoder {
+    pub fn new(rasterizer: Rasterizer) -> Self {
+        GifEncoder { rasterizer, speed: 10 }
+    }
+
+    pub fn with_speed(mut self, speed: i32) -> Self {
+        self.speed = speed.clamp(1, 30);
+        self
+    }
+}
+
+impl AnimationEncoder for GifEncoder {
+    fn encode_frames_into(&self, frames: &[Frame], buf: &mut Vec<u8>) -> io::Result<()> {
+        let mut encoder = gif::GifEncoder::new_with_speed(buf, self.speed);
+        encoder.set_repeat(Repeat::Infinite).map_err(io::Error::other)?;
+        for frame in frames {
+            let image = DynamicImage::ImageRgb8(self.rasterizer.rasterize(&frame.grid)).into_rgba8();
+            let delay = Delay::from_saturating_duration(frame.delay);
+            // Fails for grids too large for a GIF.
+            encoder.encode_frame(image::Frame::from_parts(image, 0, 0, delay)).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use image::GrayImage;
+
+use crate::error::Result;
+
+const SMALL_SYMBOLS_FILE: &[u8] = include_bytes!("../assets/symbols_small.png");
+const SMALL_SYMBOLS_WIDTH: u32 = 4;
+const SMALL_SYMBOLS_HEIGHT: u32 = 8;
+
+const BIG_SYMBOLS_FILE: &[u8] = include_bytes!("../assets/symbols_big.png");
+const BIG_SYMBOLS_WIDTH: u32 = 8;
+const BIG_SYMBOLS_HEIGHT: u32 = 16;
+
+/// First and last character in the atlas. (printable ASCII)
+pub const ASCII_START: u32 = 32;
+pub const ASCII_END: u32 = 126;
+
+/// Rendered ASCII symbols, side by side, white on black.
+pub struct GlyphAtlas {
+    image: GrayImage,
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+}
+
+impl GlyphAtlas {
+    /// Load the embedded atlas, 8x16 pixels per glyph, or 4x8 if `small`.
+    pub fn load(small: bool) -> Result<Self> {
+        let file = if small {SMALL_SYMBOLS_FILE} else {BIG_SYMBOLS_FILE};
+        Ok(GlyphAtlas {
+            image: image::load_from_memory(file)?.into_luma8(),
+            glyph_width:  if small {SMALL_SYMBOLS_WIDTH}  else {BIG_SYMBOLS_WIDTH},
+            glyph_height: if small {SMALL_SYMBOLS_HEIGHT} else {BIG_SYMBOLS_HEIGHT},
+        })
+    }
+
+    /// Index of the character in the atlas, if it's there.
+    pub fn index(c: char) -> Option<u32> {
+        let c = c as u32;
+        (ASCII_START..=ASCII_END).contains(&c).then(|| c - ASCII_START)
+    }
+
+    /// Character at the given index.
+    pub fn char(index: u32) -> char {
+        char::from_u32(ASCII_START + index).unwrap_or(' ')
+    }
+
+    /// Brightness of the pixel (`u`, `v`) of the glyph at the given index.
+    pub fn pixel(&self, index: u32, u: u32, v: u32) -> u8 {
+        self.image.get_pixel(index*self.glyph_width + u, v).0[0]
+    }
+}
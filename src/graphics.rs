@@ -0,0 +1,59 @@
+use std::io::{self, Cursor};
+
+use image::{imageops::FilterType, DynamicImage, ImageFormat, RgbaImage};
+
+use crate::options::RenderOptions;
+
+/// Draws images with pixels instead of characters, in terminals that support a graphics protocol.
+pub trait GraphicsEncoder {
+    /// Append the escape sequences that draw the image over `width`x`height` cells, starting at the cursor.
+    fn encode_image_into(&self, image: &DynamicImage, width: u32, height: u32, buf: &mut Vec<u8>) -> io::Result<()>;
+}
+
+/// How images are scaled to the pixels of the terminal cells they cover.
+#[derive(Clone, Copy, Debug)]
+pub struct Scaling {
+    /// Size of a terminal cell in pixels.
+    pub cell_size: (u32, u32),
+    pub filter: FilterType,
+}
+
+impl Scaling {
+    pub fn new(cell_size: (u32, u32)) -> Self {
+        Scaling { cell_size, filter: FilterType::Nearest }
+    }
+
+    /// Cells of the current terminal, scaled with the filter of the options.
+    pub fn for_options(options: &RenderOptions) -> Self {
+        Scaling::new(cell_size(options.aspect)).with_filter(options.filter_type())
+    }
+
+    pub fn with_filter(mut self, filter: FilterType) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Scale the image to cover `width`x`height` cells.
+    pub fn resize(&self, image: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+        image.resize_exact(width * self.cell_size.0, height * self.cell_size.1, self.filter).into_rgba8()
+    }
+
+    /// Scale the image to cover `width`x`height` cells and encode it as PNG, for the protocols that send image files.
+    pub fn encode_png(&self, image: &DynamicImage, width: u32, height: u32) -> io::Result<Vec<u8>> {
+        let mut png = Cursor::new(Vec::new());
+        self.resize(image, width, height).write_to(&mut png, ImageFormat::Png).map_err(io::Error::other)?;
+        Ok(png.into_inner())
+    }
+}
+
+/// Size of a terminal cell in pixels.
+///
+/// If the terminal doesn't report it, cells are assumed to be 8 pixels wide, with the given aspect ratio.
+pub fn cell_size(aspect: f32) -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            ((size.width / size.columns) as u32, (size.height / size.rows) as u32)
+        },
+        _ => (8, (8.0 * aspect).round().max(1.0) as u32),
+    }
+}
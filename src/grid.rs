@@ -0,0 +1,99 @@
+use std::{ops::BitOr, time::Duration};
+
+use image::Rgba;
+
+/// Text attributes of a cell, combined with `|`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    pub const NONE: Attributes = Attributes(0);
+    pub const BOLD: Attributes = Attributes(1);
+    pub const UNDERLINE: Attributes = Attributes(2);
+
+    pub fn contains(self, other: Attributes) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Attributes {
+    type Output = Attributes;
+
+    fn bitor(self, rhs: Attributes) -> Attributes {
+        Attributes(self.0 | rhs.0)
+    }
+}
+
+/// A single character of the output with its styling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub fg: Rgba<u8>,
+    /// Background color. `None` leaves the terminal background untouched.
+    pub bg: Option<Rgba<u8>>,
+    pub attrs: Attributes,
+}
+
+impl Cell {
+    pub fn new(glyph: char, fg: Rgba<u8>, bg: Option<Rgba<u8>>) -> Self {
+        Cell { glyph, fg, bg, attrs: Attributes::NONE }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell::new(' ', Rgba([255, 255, 255, 255]), None)
+    }
+}
+
+/// A rectangular grid of cells, stored row by row. This is what every mode produces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    /// Create a grid filled with empty cells.
+    pub fn new(width: u32, height: u32) -> Self {
+        Grid { width, height, cells: vec![Cell::default(); (width * height) as usize] }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> &Cell {
+        &self.cells[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, cell: Cell) {
+        self.cells[(y * self.width + x) as usize] = cell;
+    }
+
+    /// Iterate over the rows of the grid, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        // `max(1)` keeps `chunks` from panicking on an empty grid.
+        self.cells.chunks(self.width.max(1) as usize)
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+}
+
+/// A grid of an animation with the time it stays on screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub grid: Grid,
+    pub delay: Duration,
+}
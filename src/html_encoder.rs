@@ -0,0 +1,126 @@
+use std::{collections::HashMap, io::{self, Write}};
+
+use image::Rgb;
+
+use crate::{colors::{displayed_color, page_colors, to_hex}, encoder::{push_xml_escaped, ColorDepth, Encoder}, grid::{Attributes, Cell, Grid}};
+
+/// Foreground and background of a run of cells. `None` is the default color of the page.
+type Style = (Option<Rgb<u8>>, Option<Rgb<u8>>);
+
+/// Emits a self-contained HTML document with the art in a `<pre>` element.
+pub struct HtmlEncoder {
+    pub depth: ColorDepth,
+    /// Use CSS classes instead of inline styles.
+    pub classes: bool,
+    /// Draw on a dark page, see [`page_colors`].
+    pub dark: bool,
+}
+
+impl HtmlEncoder {
+    pub fn new(depth: ColorDepth) -> Self {
+        HtmlEncoder { depth, classes: false, dark: true }
+    }
+
+    pub fn with_classes(mut self, classes: bool) -> Self {
+        self.classes = classes;
+        self
+    }
+
+    pub fn with_dark(mut self, dark: bool) -> Self {
+        self.dark = dark;
+        self
+    }
+
+    fn style(&self, cell: &Cell) -> Style {
+        let fg = match self.depth {
+            ColorDepth::Monochrome => None,
+            depth => Some(displayed_color(cell.fg, depth)),
+        };
+        (fg, cell.bg.map(|bg| displayed_color(bg, self.depth)))
+    }
+
+    /// Split a row into runs of cells with the same style.
+    fn runs<'a>(&self, row: &'a [Cell]) -> Vec<(Style, &'a [Cell])> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        while start < row.len() {
+            let style = self.style(&row[start]);
+            let mut end = start + 1;
+            while end < row.len() && self.continues(&style, &row[end]) {
+                end += 1;
+            }
+            runs.push((style, &row[start..end]));
+            start = end;
+        }
+        runs
+    }
+
+    /// Whether the cell can be added to a run of the given style.
+    fn continues(&self, style: &Style, cell: &Cell) -> bool {
+        let (fg, bg) = self.style(cell);
+        // The color of a space is invisible.
+        let fg_matches = fg == style.0 || (cell.glyph == ' ' && !cell.attrs.contains(Attributes::UNDERLINE));
+        fg_matches && bg == style.1
+    }
+}
+
+fn css(style: &Style) -> String {
+    let mut css = String::new();
+    if let Some(fg) = style.0 {
+        css.push_str(&format!("color:{};", to_hex(fg)));
+    }
+    if let Some(bg) = style.1 {
+        css.push_str(&format!("background-color:{};", to_hex(bg)));
+    }
+    css
+}
+
+impl Encoder for HtmlEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        let rows: Vec<_> = grid.rows().map(|row| self.runs(row)).collect();
+
+        // Number the distinct styles in the order of appearance.
+        let mut classes: HashMap<Style, usize> = HashMap::new();
+        let mut class_order = Vec::new();
+        if self.classes {
+            for (style, _) in rows.iter().flatten() {
+                if !classes.contains_key(style) {
+                    classes.insert(*style, class_order.len());
+                    class_order.push(*style);
+                }
+            }
+        }
+
+        let (page_fg, page_bg) = page_colors(self.dark);
+
+        write!(buf, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>uniart</title>\n<style>\n")?;
+        writeln!(buf, "pre.uniart {{ font-family: monospace; line-height: 1.2; color: {}; background-color: {}; display: inline-block; padding: 0.5em; }}", to_hex(page_fg), to_hex(page_bg))?;
+        for (i, style) in class_order.iter().enumerate() {
+            writeln!(buf, ".u{i} {{ {} }}", css(style))?;
+        }
+        write!(buf, "</style>\n</head>\n<body>\n<pre class=\"uniart\">")?;
+
+        for runs in rows {
+            for (style, cells) in runs {
+                let styled = style != (None, None);
+                if styled {
+                    if self.classes {
+                        write!(buf, "<span class=\"u{}\">", classes[&style])?;
+                    }
+                    else {
+                        write!(buf, "<span style=\"{}\">", css(&style))?;
+                    }
+                }
+                for cell in cells {
+                    push_xml_escaped(buf, cell.glyph);
+                }
+                if styled {
+                    buf.extend_from_slice(b"</span>");
+                }
+            }
+            buf.push(b'\n');
+        }
+
+        write!(buf, "</pre>\n</body>\n</html>\n")
+    }
+}
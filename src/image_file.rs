@@ -1,6 +1,8 @@
-use std::{fs::File, io::BufReader, path::Path};
+use std::{fs::File, io::{BufRead, BufReader, Cursor, Read, Seek}, path::Path};
 
-use image::{codecs::gif::GifDecoder, io::Reader, AnimationDecoder, Delay, DynamicImage, ImageDecoder, ImageError};
+use image::{codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder}, AnimationDecoder, Delay, DynamicImage, Frame, ImageDecoder, ImageFormat, ImageReader};
+
+use crate::{error::{Result, UniartError}, image_sequence, y4m_decoder::{self, Y4mFrames}};
 
 pub struct GifFrame {
     pub image: DynamicImage, 
@@ -14,29 +16,92 @@ impl GifFrame {
     }
 }
 
+/// A still image, or the frames of an animated GIF, APNG, WebP or a YUV4MPEG2 video with the size of the animation.
 pub enum ImageFile {
     Image(DynamicImage), Gif(Vec<GifFrame>, (u32, u32))
 }
 
 impl ImageFile {
-    pub fn open<P>(path: P) -> Result<Self, ImageError>
+    pub fn open<P>(path: P) -> Result<Self>
     where P: AsRef<Path> {
-        let reader = Reader::open(&path)?.with_guessed_format()?;
-        match reader.format().expect("Invalid format.") {
-            image::ImageFormat::Gif => {
-                drop(reader); // make sure the file is not locked
-                let gif_file = BufReader::new(File::open(&path)?);
-                let decoder = GifDecoder::new(gif_file).unwrap();
+        Self::decode(BufReader::new(File::open(path)?))
+    }
+
+    /// Whether the path is a pattern of numbered images rather than a file, e.g. `frame_%04d.png` or `frame_*.png`.
+    pub fn is_sequence(path: &str) -> bool {
+        !Path::new(path).exists() && image_sequence::is_pattern(path)
+    }
+
+    /// Open the images matched by a printf-style or glob pattern as the frames of an animation, in the order of their numbers.
+    ///
+    /// Animated images contribute their first frame. The size of the animation is the size of the first image.
+    pub fn open_sequence(pattern: &str, delay: Delay) -> Result<Self> {
+        let mut frames = Vec::new();
+        for path in image_sequence::paths(pattern)? {
+            let image = match Self::open(path)? {
+                ImageFile::Image(image) => image,
+                ImageFile::Gif(mut frames, _) if !frames.is_empty() => frames.swap_remove(0).image,
+                ImageFile::Gif(..) => continue,
+            };
+            frames.push(GifFrame { image, delay });
+        }
+
+        let dims = frames.first().map_or((0, 0), |frame| (frame.image.width(), frame.image.height()));
+        Ok(ImageFile::Gif(frames, dims))
+    }
+
+    /// Read the whole image from a stream that can't seek, like stdin.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::decode(Cursor::new(bytes))
+    }
+
+    /// Decode an image of any supported format, guessed from its content.
+    pub fn decode<R: BufRead + Seek>(mut reader: R) -> Result<Self> {
+        if y4m_decoder::is_y4m(&mut reader)? {
+            let frames = Y4mFrames::new(reader)?;
+            let (dims, delay) = (frames.dimensions(), frames.delay());
+            let frames = frames.map(|image| image.map(|image| GifFrame { image, delay })).collect::<Result<_>>()?;
+            return Ok(ImageFile::Gif(frames, dims));
+        }
+
+        let reader = ImageReader::new(reader).with_guessed_format()?;
+        let format = reader.format().ok_or_else(|| UniartError::UnsupportedFormat(String::from("unrecognized image format")))?;
+        match format {
+            ImageFormat::Gif => {
+                let decoder = GifDecoder::new(reader.into_inner())?;
                 let dims = decoder.dimensions();
-                let frames = decoder.into_frames().collect_frames()?;
-                let frames = frames.into_iter().map(|f| {
-                    GifFrame{
-                        delay: f.delay(),
-                        image: DynamicImage::ImageRgba8(f.into_buffer()),
+                Ok(Self::from_frames(decoder.into_frames().collect_frames()?, dims))
+            },
+            ImageFormat::Png => {
+                let decoder = PngDecoder::new(reader.into_inner())?;
+                if decoder.is_apng()? {
+                    let dims = decoder.dimensions();
+                    Ok(Self::from_frames(decoder.apng()?.into_frames().collect_frames()?, dims))
+                }
+                else {
+                    Ok(ImageFile::Image(DynamicImage::from_decoder(decoder)?))
+                }
+            },
+            ImageFormat::WebP => {
+                let decoder = WebPDecoder::new(reader.into_inner())?;
+                if decoder.has_animation() {
+                    let dims = decoder.dimensions();
+                    // Some versions of `image` end animated WebPs with an error, so any error after the first frame ends the animation.
+                    let mut frames = Vec::new();
+                    for frame in decoder.into_frames() {
+                        match frame {
+                            Ok(frame) => frames.push(frame),
+                            Err(_) if !frames.is_empty() => break,
+                            Err(e) => return Err(e.into()),
+                        }
                     }
-                }).collect();
-
-                Ok(ImageFile::Gif(frames, dims))
+                    Ok(Self::from_frames(frames, dims))
+                }
+                else {
+                    Ok(ImageFile::Image(DynamicImage::from_decoder(decoder)?))
+                }
             },
             _ => {
                 Ok(ImageFile::Image(reader.decode()?))
@@ -44,10 +109,197 @@ impl ImageFile {
         }
     }
 
+    fn from_frames(frames: Vec<Frame>, dims: (u32, u32)) -> Self {
+        let frames = frames.into_iter().map(|f| {
+            GifFrame{
+                delay: f.delay(),
+                image: DynamicImage::ImageRgba8(f.into_buffer()),
+            }
+        }).collect();
+
+        ImageFile::Gif(frames, dims)
+    }
+
     pub fn dimensions(&self) -> (u32, u32) {
         match self {
             ImageFile::Image(image) => (image.width(), image.height()),
             ImageFile::Gif(_, dims) => *dims,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::{png::PngEncoder, webp::WebPEncoder}, ExtendedColorType, ImageEncoder, Rgba, RgbaImage};
+
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// Frames of 3x2 pixels, so that a swapped width and height shows.
+    fn frame(color: Rgba<u8>) -> RgbaImage {
+        RgbaImage::from_pixel(3, 2, color)
+    }
+
+    /// The chunks of a PNG file after the signature, as type and data.
+    fn png_chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut chunks = Vec::new();
+        let mut i = 8;
+        while i < png.len() {
+            let len = u32::from_be_bytes(png[i..i + 4].try_into().unwrap()) as usize;
+            chunks.push((png[i + 4..i + 8].try_into().unwrap(), png[i + 8..i + 8 + len].to_vec()));
+            // Skip the checksum.
+            i += 12 + len;
+        }
+        chunks
+    }
+
+    /// The data of the first chunk of the given type in a RIFF file.
+    fn riff_chunk<'a>(riff: &'a [u8], kind: &[u8; 4]) -> &'a [u8] {
+        // Skip the RIFF header and the WEBP form type.
+        let mut i = 12;
+        loop {
+            let len = u32::from_le_bytes(riff[i + 4..i + 8].try_into().unwrap()) as usize;
+            if &riff[i..i + 4] == kind {
+                return &riff[i + 8..i + 8 + len];
+            }
+            // Chunks are padded to an even size.
+            i += 8 + len + len % 2;
+        }
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in bytes {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {(crc >> 1) ^ 0xedb8_8320} else {crc >> 1};
+            }
+        }
+        !crc
+    }
+
+    fn push_png_chunk(buf: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = buf.len();
+        buf.extend_from_slice(kind);
+        buf.extend_from_slice(data);
+        let crc = crc32(&buf[start..]);
+        buf.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    fn push_riff_chunk(buf: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        buf.extend_from_slice(kind);
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            buf.push(0);
+        }
+    }
+
+    fn push_u24(buf: &mut Vec<u8>, n: u32) {
+        buf.extend_from_slice(&n.to_le_bytes()[..3]);
+    }
+
+    fn png(image: &RgbaImage) -> Vec<u8> {
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).write_image(image, image.width(), image.height(), ExtendedColorType::Rgba8).unwrap();
+        png
+    }
+
+    /// An APNG with a frame of every image, 100ms each, from the image data of still PNGs.
+    fn apng(images: &[RgbaImage]) -> Vec<u8> {
+        let mut buf = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut sequence = 0u32;
+        for (n, image) in images.iter().enumerate() {
+            let chunks = png_chunks(&png(image));
+            if n == 0 {
+                push_png_chunk(&mut buf, b"IHDR", &chunks[0].1);
+                let actl = [(images.len() as u32).to_be_bytes(), 0u32.to_be_bytes()].concat();
+                push_png_chunk(&mut buf, b"acTL", &actl);
+            }
+
+            // Full size frame at the origin, 1/10 s, no disposal and no blending.
+            let mut fctl = Vec::new();
+            for n in [sequence, image.width(), image.height(), 0, 0] {
+                fctl.extend_from_slice(&n.to_be_bytes());
+            }
+            fctl.extend_from_slice(&[0, 1, 0, 10, 0, 0]);
+            push_png_chunk(&mut buf, b"fcTL", &fctl);
+            sequence += 1;
+
+            // The first frame is the default image, the others are in frame data chunks.
+            for (_, data) in chunks.iter().filter(|(kind, _)| kind == b"IDAT") {
+                if n == 0 {
+                    push_png_chunk(&mut buf, b"IDAT", data);
+                }
+                else {
+                    push_png_chunk(&mut buf, b"fdAT", &[&sequence.to_be_bytes()[..], data].concat());
+                    sequence += 1;
+                }
+            }
+        }
+        push_png_chunk(&mut buf, b"IEND", &[]);
+        buf
+    }
+
+    /// An animated WebP with a frame of every image, 100ms each, from the bitstreams of still lossless WebPs.
+    fn animated_webp(images: &[RgbaImage]) -> Vec<u8> {
+        let (width, height) = images[0].dimensions();
+        let mut body = b"WEBP".to_vec();
+
+        // Animation flag, canvas size.
+        let mut vp8x = vec![0b0000_0010, 0, 0, 0];
+        push_u24(&mut vp8x, width - 1);
+        push_u24(&mut vp8x, height - 1);
+        push_riff_chunk(&mut body, b"VP8X", &vp8x);
+        // Black background, infinite loop.
+        push_riff_chunk(&mut body, b"ANIM", &[0, 0, 0, 255, 0, 0]);
+
+        for image in images {
+            let mut webp = Vec::new();
+            WebPEncoder::new_lossless(&mut webp).encode(image, width, height, ExtendedColorType::Rgba8).unwrap();
+            let vp8l = riff_chunk(&webp, b"VP8L");
+
+            // At the origin, full size, 100ms, no blending.
+            let mut anmf = Vec::new();
+            for n in [0, 0, width - 1, height - 1, 100] {
+                push_u24(&mut anmf, n);
+            }
+            anmf.push(0b0000_0010);
+            push_riff_chunk(&mut anmf, b"VP8L", vp8l);
+            push_riff_chunk(&mut body, b"ANMF", &anmf);
+        }
+
+        let mut buf = Vec::new();
+        push_riff_chunk(&mut buf, b"RIFF", &body);
+        buf
+    }
+
+    fn assert_animation(file: ImageFile, colors: &[Rgba<u8>]) {
+        let ImageFile::Gif(frames, dims) = file else { panic!("expected an animation") };
+        assert_eq!(dims, (3, 2));
+        assert_eq!(frames.len(), colors.len());
+        for (frame, &color) in frames.iter().zip(colors) {
+            assert_eq!(frame.image.to_rgba8(), RgbaImage::from_pixel(3, 2, color));
+            assert_eq!(frame.dalay_as_duration(), std::time::Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn apng_is_an_animation() {
+        assert_animation(ImageFile::decode(Cursor::new(apng(&[frame(RED), frame(BLUE)]))).unwrap(), &[RED, BLUE]);
+    }
+
+    #[test]
+    fn animated_webp_is_an_animation() {
+        assert_animation(ImageFile::decode(Cursor::new(animated_webp(&[frame(RED), frame(BLUE)]))).unwrap(), &[RED, BLUE]);
+    }
+
+    #[test]
+    fn still_png_is_an_image() {
+        let ImageFile::Image(image) = ImageFile::decode(Cursor::new(png(&frame(RED)))).unwrap() else { panic!("expected a still image") };
+        assert_eq!(image.to_rgba8(), frame(RED));
+    }
+}
@@ -0,0 +1,192 @@
+use std::{io, path::PathBuf};
+
+use glob::Pattern;
+
+use crate::error::{Result, UniartError};
+
+/// A file name with a printf-style number, like `frame_%04d.png`.
+struct NumberedName<'a> {
+    /// Directory of the files, with a trailing separator if any.
+    dir: &'a str,
+    prefix: String,
+    /// Minimal width of the number.
+    width: usize,
+    /// Whether the number is padded to the width with zeros rather than spaces.
+    zero_padded: bool,
+    suffix: String,
+}
+
+impl<'a> NumberedName<'a> {
+    /// Parse a `%d`, `%0Nd` or `%Nd` conversion in the file name. `%%` is a literal percent sign.
+    fn parse(pattern: &'a str) -> Option<Self> {
+        let name_start = pattern.rfind(['/', '\\']).map_or(0, |i| i + 1);
+        let (dir, name) = pattern.split_at(name_start);
+
+        let mut parts = (String::new(), String::new());
+        let mut width = None;
+        let mut zero_padded = false;
+        let mut chars = name.chars().peekable();
+        while let Some(c) = chars.next() {
+            let part = if width.is_none() {&mut parts.0} else {&mut parts.1};
+            if c != '%' {
+                part.push(c);
+                continue;
+            }
+            if chars.next_if_eq(&'%').is_some() {
+                part.push('%');
+                continue;
+            }
+
+            // Only one number is allowed.
+            if width.is_some() {
+                return None;
+            }
+            let mut digits = String::new();
+            while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                digits.push(d);
+            }
+            if chars.next() != Some('d') {
+                return None;
+            }
+            width = Some(digits.parse().unwrap_or(0));
+            zero_padded = digits.starts_with('0');
+        }
+
+        Some(NumberedName { dir, prefix: parts.0, width: width?, zero_padded, suffix: parts.1 })
+    }
+
+    /// The number in a file name that matches the pattern, only if it's written the way printf would write it.
+    fn number(&self, name: &str) -> Option<u64> {
+        let digits = name.strip_prefix(&self.prefix)?.strip_suffix(&self.suffix)?;
+        let unpadded = digits.trim_start_matches(' ');
+        if unpadded.is_empty() || !unpadded.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let number = unpadded.parse().ok()?;
+        let width = self.width;
+        let printed = if self.zero_padded {format!("{number:0width$}")} else {format!("{number:width$}")};
+        (printed == digits).then_some(number)
+    }
+}
+
+/// Whether the path is a printf-style or glob pattern rather than a file name.
+pub fn is_pattern(path: &str) -> bool {
+    NumberedName::parse(path).is_some() || path.contains(['*', '?', '['])
+}
+
+/// Paths of the images matched by the pattern, in the order of their numbers.
+pub fn paths(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut numbered: Vec<(u64, PathBuf)> = match NumberedName::parse(pattern) {
+        Some(name) => {
+            let glob = format!("{}{}*{}", Pattern::escape(name.dir), Pattern::escape(&name.prefix), Pattern::escape(&name.suffix));
+            glob_paths(&glob)?.into_iter()
+                .filter_map(|path| {
+                    let number = name.number(path.file_name()?.to_str()?)?;
+                    Some((number, path))
+                })
+                .collect()
+        },
+        None => glob_paths(pattern)?.into_iter().map(|path| (0, path)).collect(),
+    };
+    if numbered.is_empty() {
+        return Err(UniartError::Io(io::Error::new(io::ErrorKind::NotFound, format!("no files match {pattern}"))));
+    }
+
+    // Glob patterns are ordered by the numbers in the names, so that frame_10 comes after frame_9.
+    numbered.sort_by_cached_key(|(number, path)| (*number, natural_key(&path.to_string_lossy())));
+    Ok(numbered.into_iter().map(|(_, path)| path).collect())
+}
+
+fn glob_paths(pattern: &str) -> Result<Vec<PathBuf>> {
+    let paths = glob::glob(pattern).map_err(|e| UniartError::InvalidOptions(format!("invalid pattern {pattern}: {e}")))?;
+    paths.filter(|path| path.as_ref().map_or(true, |path| path.is_file()))
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| UniartError::Io(io::Error::new(e.error().kind(), e.to_string())))
+}
+
+/// Part of a name for natural ordering, numbers compare by value and come before text like digits do.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    Number(u128),
+    Text(String),
+}
+
+fn natural_key(name: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        let digit = c.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != digit).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(if digit {Chunk::Number(chunk.parse().unwrap_or(u128::MAX))} else {Chunk::Text(chunk.to_string())});
+        rest = tail;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_splits_the_name_around_the_number() {
+        let name = NumberedName::parse("shots/frame_%04d.png").unwrap();
+        assert_eq!((name.dir, name.prefix.as_str(), name.width, name.zero_padded, name.suffix.as_str()), ("shots/", "frame_", 4, true, ".png"));
+
+        let name = NumberedName::parse("%d%%.png").unwrap();
+        assert_eq!((name.dir, name.prefix.as_str(), name.width, name.zero_padded, name.suffix.as_str()), ("", "", 0, false, "%.png"));
+
+        let name = NumberedName::parse("frame_%4d.png").unwrap();
+        assert_eq!((name.width, name.zero_padded), (4, false));
+    }
+
+    #[test]
+    fn parse_rejects_names_without_one_number() {
+        for pattern in ["frame.png", "100%%.png", "%d_%d.png", "frame_%s.png", "frame_%-4d.png", "frame_%4.png", "%d/frame.png"] {
+            assert!(NumberedName::parse(pattern).is_none(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn number_is_printed_with_the_width() {
+        let name = NumberedName::parse("frame_%04d.png").unwrap();
+        assert_eq!(name.number("frame_0001.png"), Some(1));
+        assert_eq!(name.number("frame_0000.png"), Some(0));
+        assert_eq!(name.number("frame_12345.png"), Some(12345));
+        for other in ["frame_00001.png", "frame_001.png", "frame_1.png", "frame_   1.png", "frame_+001.png", "frame_.png", "frame_0001.jpg", "shot_0001.png"] {
+            assert_eq!(name.number(other), None, "{other}");
+        }
+    }
+
+    #[test]
+    fn number_without_padding() {
+        let name = NumberedName::parse("frame_%d.png").unwrap();
+        assert_eq!(name.number("frame_0.png"), Some(0));
+        assert_eq!(name.number("frame_10.png"), Some(10));
+        assert_eq!(name.number("frame_010.png"), None);
+    }
+
+    #[test]
+    fn number_padded_with_spaces() {
+        let name = NumberedName::parse("frame_%4d.png").unwrap();
+        assert_eq!(name.number("frame_   1.png"), Some(1));
+        assert_eq!(name.number("frame_12345.png"), Some(12345));
+        assert_eq!(name.number("frame_0001.png"), None);
+        assert_eq!(name.number("frame_1.png"), None);
+    }
+
+    #[test]
+    fn natural_order_compares_numbers_by_value() {
+        let mut names = ["frame_10.png", "frame_9.png", "frame_100.png", "frame_1.png", "b_2.png", "a_10.png"];
+        names.sort_by_key(|name| natural_key(name));
+        assert_eq!(names, ["a_10.png", "b_2.png", "frame_1.png", "frame_9.png", "frame_10.png", "frame_100.png"]);
+    }
+
+    #[test]
+    fn natural_order_of_chunks() {
+        assert!(natural_key("shot9_2") < natural_key("shot10_1"));
+        assert!(natural_key("shot") < natural_key("shot1"));
+        assert!(natural_key("10") < natural_key("a"));
+    }
+}
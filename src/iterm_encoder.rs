@@ -0,0 +1,37 @@
+use std::io;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::DynamicImage;
+
+use crate::{encoder::push_u32, graphics::{GraphicsEncoder, Scaling}};
+
+/// Draws the image with the inline image protocol of iTerm2 (`OSC 1337 File=`), sent as PNG.
+///
+/// Also supported by e.g. WezTerm and the terminal of VS Code.
+pub struct ItermEncoder {
+    pub scaling: Scaling,
+}
+
+impl ItermEncoder {
+    pub fn new(scaling: Scaling) -> Self {
+        ItermEncoder { scaling }
+    }
+}
+
+impl GraphicsEncoder for ItermEncoder {
+    fn encode_image_into(&self, image: &DynamicImage, width: u32, height: u32, buf: &mut Vec<u8>) -> io::Result<()> {
+        let png = self.scaling.encode_png(image, width, height)?;
+
+        // Display the file inline, stretched over the given cells.
+        buf.extend_from_slice(b"\x1b]1337;File=inline=1;size=");
+        push_u32(buf, png.len() as u32);
+        buf.extend_from_slice(b";width=");
+        push_u32(buf, width);
+        buf.extend_from_slice(b";height=");
+        push_u32(buf, height);
+        buf.extend_from_slice(b";preserveAspectRatio=0:");
+        buf.extend_from_slice(STANDARD.encode(png).as_bytes());
+        buf.push(0x07);
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use std::io;
+
+use image::{Pixel, Rgba};
+use serde::Serialize;
+
+use crate::{colors::to_hex, encoder::AnimationEncoder, grid::{Cell, Frame}};
+
+/// The rendered art as a tree of plain data, shared by the data formats.
+#[derive(Serialize)]
+pub(crate) struct Document<'a> {
+    width: u32,
+    height: u32,
+    mode: &'a str,
+    frames: Vec<FrameData>,
+}
+
+#[derive(Serialize)]
+struct FrameData {
+    /// How long the frame stays on screen, 0 for still images.
+    delay_ms: u64,
+    /// Cells row by row.
+    cells: Vec<CellData>,
+}
+
+#[derive(Serialize)]
+struct CellData {
+    glyph: char,
+    /// `#rrggbb` hex code.
+    fg: String,
+    /// `#rrggbb` hex code, or nothing for the default background.
+    bg: Option<String>,
+}
+
+impl<'a> Document<'a> {
+    /// Describe the frames, which all have the size of the first one.
+    pub(crate) fn new(mode: &'a str, frames: &[Frame]) -> Self {
+        let (width, height) = frames.first().map_or((0, 0), |frame| (frame.grid.width(), frame.grid.height()));
+        Document {
+            width,
+            height,
+            mode,
+            frames: frames.iter().map(|frame| FrameData {
+                delay_ms: frame.delay.as_millis() as u64,
+                cells: frame.grid.cells().iter().map(CellData::new).collect(),
+            }).collect(),
+        }
+    }
+}
+
+impl CellData {
+    fn new(cell: &Cell) -> Self {
+        let hex = |col: Rgba<u8>| to_hex(col.to_rgb());
+        CellData { glyph: cell.glyph, fg: hex(cell.fg), bg: cell.bg.map(hex) }
+    }
+}
+
+/// Emits the cells with their glyphs and colors as JSON, for programs that draw the art themselves.
+///
+/// Colors are the ones chosen by the mode, not reduced to any color depth.
+/// A single grid is emitted as a single frame.
+pub struct JsonEncoder {
+    /// Name of the mode that rendered the grids.
+    pub mode: String,
+}
+
+impl JsonEncoder {
+    pub fn new(mode: impl Into<String>) -> Self {
+        JsonEncoder { mode: mode.into() }
+    }
+}
+
+impl AnimationEncoder for JsonEncoder {
+    fn encode_frames_into(&self, frames: &[Frame], buf: &mut Vec<u8>) -> io::Result<()> {
+        let document = Document::new(&self.mode, frames);
+        serde_json::to_writer(&mut *buf, &document)?;
+        buf.push(b'\n');
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::io;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::DynamicImage;
+
+use crate::{encoder::push_u32, graphics::{GraphicsEncoder, Scaling}};
+
+/// Maximum size of the base64 data in one escape sequence.
+const CHUNK_SIZE: usize = 4096;
+
+/// Draws the image with the kitty graphics protocol, sent as PNG.
+///
+/// Every image is sent with the same id and placement id, so that the frames of an animation replace each other.
+pub struct KittyEncoder {
+    pub scaling: Scaling,
+    /// Id of the image in the terminal. Defaults to the id of the process, so that separate runs don't replace each other's images.
+    pub id: u32,
+}
+
+impl KittyEncoder {
+    pub fn new(scaling: Scaling) -> Self {
+        KittyEncoder { scaling, id: std::process::id().max(1) }
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id.max(1);
+        self
+    }
+}
+
+impl GraphicsEncoder for KittyEncoder {
+    fn encode_image_into(&self, image: &DynamicImage, width: u32, height: u32, buf: &mut Vec<u8>) -> io::Result<()> {
+        let data = STANDARD.encode(self.scaling.encode_png(image, width, height)?);
+
+        let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK_SIZE).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            buf.extend_from_slice(b"\x1b_G");
+            if i == 0 {
+                // Transmit and display a PNG over the given cells, without responses from the terminal.
+                buf.extend_from_slice(b"a=T,f=100,q=2,p=1,i=");
+                push_u32(buf, self.id);
+                buf.extend_from_slice(b",c=");
+                push_u32(buf, width);
+                buf.extend_from_slice(b",r=");
+                push_u32(buf, height);
+                buf.push(b',');
+            }
+            buf.extend_from_slice(if i + 1 < chunks.len() {b"m=1;"} else {b"m=0;"});
+            buf.extend_from_slice(chunk);
+            buf.extend_from_slice(b"\x1b\\");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    /// Split the output into the keys and the payload of every escape sequence.
+    fn commands(buf: &[u8]) -> Vec<(String, &[u8])> {
+        let text = std::str::from_utf8(buf).unwrap();
+        let text = text.strip_prefix("\x1b_G").expect("start of a command").strip_suffix("\x1b\\").expect("end of a command");
+        text.split("\x1b\\\x1b_G")
+            .map(|command| {
+                let (keys, payload) = command.split_once(';').expect("payload");
+                (keys.to_owned(), payload.as_bytes())
+            })
+            .collect()
+    }
+
+    /// Noise, so that the PNG doesn't compress and takes several chunks.
+    fn noise(width: u32, height: u32) -> DynamicImage {
+        let mut state = 1u32;
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |_, _| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            Rgba(state.to_le_bytes())
+        }))
+    }
+
+    fn encode(encoder: &KittyEncoder, image: &DynamicImage) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encoder.encode_image_into(image, image.width(), image.height(), &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn data_is_sent_in_chunks_of_at_most_4096_bytes() {
+        let image = noise(64, 64);
+        let buf = encode(&KittyEncoder::new(Scaling::new((1, 1))).with_id(7), &image);
+        let commands = commands(&buf);
+        assert!(commands.len() > 2);
+
+        let (last, rest) = commands.split_last().unwrap();
+        assert!(rest.iter().all(|(_, payload)| payload.len() == CHUNK_SIZE));
+        assert!(!last.1.is_empty() && last.1.len() <= CHUNK_SIZE);
+
+        let data: Vec<u8> = commands.iter().flat_map(|(_, payload)| payload.iter().copied()).collect();
+        let png = image::load_from_memory(&STANDARD.decode(data).unwrap()).unwrap();
+        assert_eq!(png.to_rgba8(), image.to_rgba8());
+    }
+
+    #[test]
+    fn only_the_first_chunk_has_the_keys() {
+        let buf = encode(&KittyEncoder::new(Scaling::new((1, 1))).with_id(7), &noise(64, 64));
+        let commands = commands(&buf);
+        assert_eq!(commands[0].0, "a=T,f=100,q=2,p=1,i=7,c=64,r=64,m=1");
+        for (keys, _) in &commands[1..commands.len() - 1] {
+            assert_eq!(keys, "m=1");
+        }
+        assert_eq!(commands.last().unwrap().0, "m=0");
+    }
+
+    #[test]
+    fn a_single_chunk_is_the_last() {
+        let buf = encode(&KittyEncoder::new(Scaling::new((1, 1))).with_id(7), &noise(2, 2));
+        let commands = commands(&buf);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].0, "a=T,f=100,q=2,p=1,i=7,c=2,r=2,m=0");
+    }
+
+    #[test]
+    fn frames_replace_the_same_placement() {
+        let encoder = KittyEncoder::new(Scaling::new((1, 1)));
+        let first = encode(&encoder, &noise(2, 2));
+        let second = encode(&encoder, &DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]))));
+        let keys = |buf: &[u8]| {
+            let mut keys: Vec<String> = commands(buf)[0].0.split(',').filter(|key| key.starts_with("i=") || key.starts_with("p=")).map(String::from).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(&first).len(), 2);
+        assert_eq!(keys(&first), keys(&second));
+    }
+}
@@ -0,0 +1,96 @@
+//! Converts images to unicode art.
+//!
+//! Every mode renders an image into a [`Grid`] of styled cells without touching the terminal.
+//! An [`Encoder`] then turns the grid into the output format, e.g. text with ansi escape sequences.
+
+mod luminance_printer;
+mod colors;
+mod pixels_printer;
+mod braille_printer;
+mod edges_printer;
+mod shape_printer;
+mod glyph_atlas;
+mod options;
+mod renderer;
+mod grid;
+mod encoder;
+mod ansi_encoder;
+mod plain_encoder;
+mod html_encoder;
+mod svg_encoder;
+mod rasterizer;
+mod png_encoder;
+mod gif_encoder;
+mod cast_encoder;
+mod ttyrec_encoder;
+mod ans_encoder;
+mod json_encoder;
+mod mirc_encoder;
+mod discord_encoder;
+mod graphics;
+mod sixel_encoder;
+mod kitty_encoder;
+mod iterm_encoder;
+#[cfg(feature = "msgpack")]
+mod msgpack_encoder;
+mod error;
+mod image_sequence;
+pub mod image_file;
+pub mod raw_stream;
+pub mod y4m_decoder;
+
+use image::{DynamicImage, GenericImageView};
+
+pub use error::{Result, UniartError};
+pub use options::{RenderOptions, RenderOptionsBuilder};
+pub use renderer::{GraphicsFactory, Mode, ModeFactory, Registry, Renderer, RendererFactory};
+pub use luminance_printer::LuminanceRenderer;
+pub use pixels_printer::{DoublePixelsRenderer, PixelsRenderer};
+pub use braille_printer::BrailleRenderer;
+pub use edges_printer::EdgesRenderer;
+pub use shape_printer::ShapesRenderer;
+pub use glyph_atlas::{GlyphAtlas, ASCII_END, ASCII_START};
+pub use grid::{Attributes, Cell, Frame, Grid};
+pub use encoder::{AnimationEncoder, ColorDepth, Encoder};
+pub use ansi_encoder::AnsiEncoder;
+pub use plain_encoder::PlainEncoder;
+pub use html_encoder::HtmlEncoder;
+pub use svg_encoder::SvgEncoder;
+pub use rasterizer::Rasterizer;
+pub use png_encoder::PngEncoder;
+pub use gif_encoder::GifEncoder;
+pub use cast_encoder::CastEncoder;
+pub use ttyrec_encoder::TtyrecEncoder;
+pub use ans_encoder::AnsEncoder;
+pub use json_encoder::JsonEncoder;
+pub use mirc_encoder::MircEncoder;
+pub use discord_encoder::DiscordEncoder;
+pub use graphics::{cell_size, GraphicsEncoder, Scaling};
+pub use sixel_encoder::SixelEncoder;
+pub use kitty_encoder::KittyEncoder;
+pub use iterm_encoder::ItermEncoder;
+#[cfg(feature = "msgpack")]
+pub use msgpack_encoder::MsgpackEncoder;
+
+/// Render an image into a grid of cells with one of the built-in modes.
+///
+/// Encode the grid with the color depth of the options adjusted by [`Registry::configure`], so that modes that need colors get them.
+/// Fails if the mode doesn't exist or the options are invalid.
+pub fn render(image: &DynamicImage, options: &RenderOptions) -> Result<Grid> {
+    let renderer = Registry::default().create(options)?;
+    let (width, height) = options.dimensions_for(image.dimensions());
+    Ok(renderer.render(image, width, height))
+}
+
+/// Draw an image as terminal output with one of the built-in modes, including the graphics modes.
+///
+/// Text modes are encoded with ansi escape sequences of the color depth in the options, adjusted by [`Registry::configure`].
+/// Fails if the mode doesn't exist or the options are invalid.
+pub fn draw(image: &DynamicImage, options: &RenderOptions) -> Result<Vec<u8>> {
+    let mut options = options.clone();
+    let mode = Registry::default().configure(&mut options)?;
+    let (width, height) = options.dimensions_for(image.dimensions());
+    let mut buf = Vec::new();
+    mode.draw_into(image, width, height, &AnsiEncoder::new(options.color_depth), &mut buf).map_err(UniartError::Write)?;
+    Ok(buf)
+}
@@ -1,6 +1,6 @@
-use image::{DynamicImage, GenericImageView, Rgba};
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgba};
 
-use crate::{colors::{reset_color, set_black_background, set_color_full_brightness}, Args};
+use crate::{colors::{background, full_brightness}, grid::{Cell, Grid}, options::RenderOptions, renderer::Renderer};
 
 // https://stackoverflow.com/questions/30097953/ascii-art-sorting-an-array-of-ascii-characters-by-brightness-levels-c-c
 // Characters are sorted by brightness, from dark to light. Brightnesses are relative and cannot be compared between palettes.
@@ -37,29 +37,45 @@ pub fn luminance(col: Rgba<u8>) -> f32 {
 }
 
 /// Returns a character that best represents the given color's luminance.
-pub fn char_from_color(col: Rgba<u8>, args: &Args) -> char {
+pub fn char_from_color(col: Rgba<u8>, invert: bool, dense: bool) -> char {
     let mut luminance = luminance(col);
-    if args.invert {luminance = 1.0 - luminance}
-    char_from_luminance(luminance, args.quality)
+    if invert {luminance = 1.0 - luminance}
+    char_from_luminance(luminance, dense)
 }
 
-pub fn print_luminance(args: &Args, image: &DynamicImage) {
-    let scaled = image.resize_exact(
-        args.width,
-        args.height, 
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
+/// Picks characters by the luminance of the pixels.
+pub struct LuminanceRenderer {
+    dense: bool,
+    invert: bool,
+    filter: FilterType,
+    background: Option<Rgba<u8>>,
+}
+
+impl LuminanceRenderer {
+    pub fn new(options: &RenderOptions) -> Self {
+        LuminanceRenderer {
+            dense: options.quality,
+            invert: options.invert,
+            filter: options.filter_type(),
+            background: background(options),
+        }
+    }
+}
+
+impl Renderer for LuminanceRenderer {
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid {
+        let scaled = image.resize_exact(width, height, self.filter);
 
-    for y in 0..scaled.height() {
-        set_black_background(args);
-        for x in 0..scaled.width() {
-            let pixel = scaled.get_pixel(x, y);
-            let char = char_from_color(pixel, args);
+        let mut grid = Grid::new(scaled.width(), scaled.height());
+        for y in 0..scaled.height() {
+            for x in 0..scaled.width() {
+                let pixel = scaled.get_pixel(x, y);
+                let char = char_from_color(pixel, self.invert, self.dense);
 
-            set_color_full_brightness(pixel, args);
-            print!("{char}");
+                grid.set(x, y, Cell::new(char, full_brightness(pixel, self.invert), self.background));
+            }
         }
-        reset_color();
-        println!()
+
+        grid
     }
-}
\ No newline at end of file
+}
@@ -1,207 +1,119 @@
-mod luminance_printer;
-mod colors;
-mod pixels_printer;
-mod braille_printer;
-mod edges_printer;
-mod shape_printer;
-mod image_file;
+mod args;
 mod gif_animator;
 
-use std::process::exit;
+use std::{fs::File, io::{self, Write}, process::exit, time::Duration};
 
 use clap::Parser;
-use gif_animator::animate_gif;
-use image::{DynamicImage, ImageBuffer, ImageError};
+use gif_animator::{animate_gif, animate_stream, Draw};
 
-use image_file::ImageFile;
-use shape_printer::{BIG_SYMBOLS_FILE, SMALL_SYMBOLS_FILE};
+use args::Args;
+use image::{Delay, DynamicImage};
+use uniart::{image_file::{GifFrame, ImageFile}, raw_stream::RawFrames, AnimationEncoder, Frame, Mode, Result, UniartError};
 
-const MODES: [&str; 6] = ["luminance", "pixels", "double-pixels", "braille", "edges", "shapes"];
-
-/// CLI app to display images in the terminal in different ways
-#[derive(Parser)]
-#[command(version = "1.0", author = "Gabriel Myszkier <myszkier.gabriel@gmail.com>", about = "Converts images to ascii art")]
-#[group()]
-struct Args {
-    /// Path to the image file
-    image: String,
-
-    /// Mode. (one of: luminance, pixels, double-pixels, braille, edges, shapes)
-    ///
-    /// Can use only the first letter of the mode as a shortcut.
-    #[arg(short, long, default_value_t = String::from("shapes"), help_heading = "Output customization")]
-    mode: String,
-    
-    /// Sets the width of the output. If set to 0 it will fill the terminal window.
-    /// 
-    /// If the terminal size cannot be determined, 100 will be used.
-    #[arg(short, long, default_value_t = 0, help_heading = "Output customization")]
-    width: u32,
-
-    /// Sets the aspect ratio of the terminal font.
-    #[arg(short, long, default_value_t = 2.0, help_heading = "Output customization")]
-    aspect: f32,
-
-    /// Sets the character palette to use. Works in shapes mode.
-    /// 
-    /// Must be subset of [SPACE], A-Z, a-z, 0-9, !"#$%&'()*+,-./:;<=>?@[\]^_`{|}~ (ASCII 32-127)
-    #[arg(short, long, help_heading = "Output customization")]
-    palette: Option<String>,
-
-    /// Outputs the image in color.
-    /// 
-    /// Ansi escape sequences are not supported by all terminals, especially on older Windows versions.
-    #[arg(short, long, help_heading = "Color options")]
-    colors: bool,
-
-    /// Uses truecolor escape sequences. (only works in some terminals)
-    #[arg(short, long, help_heading = "Color options")]
-    truecolor: bool,
-
-    /// Sets the background color to black.
-    #[arg(short, long, help_heading = "Color options")]
-    background: bool,
-
-    /// Inverts the image brightness. (useful in white-background terminals)
-    #[arg(short, long, help_heading = "Image manipulation")]
-    invert: bool,
-
-    /// Switch the quality of output. (only works for luminance, edges and shapes modes)
-    /// 
-    /// For luminance and edges modes it will use a larger palette of characters,
-    /// which will potentially result in a better looking output.
-    /// For shapes mode it will reduce the quality of character choice and improve performance.
-    #[arg(short, long, help_heading = "Image manipulation")]
-    quality: bool,
-
-    /// Uses linear filter instead of nearest neighbor when scaling the image. 
-    /// 
-    /// Results in cleaner but less crisp output.
-    #[arg(short, long, help_heading = "Image manipulation")]
-    filter: bool,
-
-
-    // Not initialized by clap, filled later.
-    #[clap(skip)]
-    height: u32,
-
-    #[clap(skip)]
-    image_file: Option<ImageFile>,
-
-    #[clap(skip)]
-    printer: Option<fn(&Args, &DynamicImage) -> ()>,
-
-    #[clap(skip)]
-    shapes_symbols: Option<ImageBuffer<image::Luma<u8>, Vec<u8>>>,
+/// Frames that are read while they're shown, from a video or a raw stream.
+struct Stream<'a> {
+    frames: Box<dyn Iterator<Item = Result<DynamicImage>> + 'a>,
+    dims: (u32, u32),
+    delay: Duration,
 }
 
-impl Args {
-    fn normalize(&mut self) -> Result<(), ImageError> {
-        if self.truecolor {
-            self.colors = true;
-        }
-
-        // If the mode is not valid, try to find a mode that starts with the given string.
-        if !MODES.contains(&self.mode.as_str()) {
-            let mode = MODES.iter().find(|&m| m.starts_with(&self.mode));
-            match mode {
-                Some(m) => self.mode = m.to_string(),
-                None => {
-                    eprintln!("Invalid mode.");
-                    exit(1);
-                },
-            }
-        }
-
-        // Set the printer function based on the mode.
-        self.printer = Some(
-            match self.mode.as_str() {
-                "luminance" =>     luminance_printer::print_luminance,
-                "pixels" =>        pixels_printer::print_pixels,
-                "double-pixels" => pixels_printer::print_double_pixels,
-                "braille" =>       braille_printer::print_braille,
-                "edges" =>         edges_printer::print_edges,
-                "shapes" =>        shape_printer::print_shapes,
-                _ => {
-                    eprintln!("Invalid mode.");
-                    exit(1);
-                }
-            });
-        if self.mode == "pixels" || self.mode == "double-pixels" {
-            self.colors = true;
-        }
-        if self.mode == "shapes" {
-            // Load the symbols image.
-            let symbols_file = if self.quality {SMALL_SYMBOLS_FILE}   else {BIG_SYMBOLS_FILE};
-
-            let symbols = image::load_from_memory(symbols_file).unwrap();
-            self.shapes_symbols = Some(symbols.into_luma8());
-
-            // Prepare the palette.
-            if let None = self.palette {
-                self.palette = Some(String::from(" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~"));
-            }
-            for c in self.palette.as_ref().unwrap().chars() {
-                if (c as u32) < shape_printer::ASCII_START || (c as u32) > shape_printer::ASCII_END {
-                    eprintln!("Invalid character in the palette: {}", c);
-                    exit(1);
-                }
-            }
-        }
-
-        // Open image file.
-        self.image_file = Some(ImageFile::open(&self.image)?);
-        
-        // Calculate the dimensions of the image in characters.
-        let img_ref = self.image_file.as_ref().unwrap();
-        let (w, h) = img_ref.dimensions();
-        let (w, h) = (w as f32, h as f32);
+fn realize(args: &mut Args) -> Result<()> {
+    let stream = match (args.video.take(), args.raw) {
+        (Some(video), _) => Some(Stream { dims: video.dimensions(), delay: video.delay().into(), frames: Box::new(video) }),
+        (None, Some(raw)) => Some(Stream { frames: Box::new(RawFrames::new(args.raw_input()?, raw)), dims: (raw.width, raw.height), delay: args.frame_delay()? }),
+        (None, None) => None,
+    };
+    let args = &*args;
+
+    let collected;
+    let image_file = match (&args.image_file, stream) {
+        (Some(image_file), _) => image_file,
+        (None, Some(stream)) if args.plays_in_terminal() => {
+            return animate_stream(args, stream.frames, stream.delay, &*terminal_draw(args)?);
+        },
+        (None, Some(stream)) => {
+            collected = collect(stream)?;
+            &collected
+        },
+        (None, None) => return Err(UniartError::InvalidOptions(String::from("arguments were not normalized"))),
+    };
+
+    match image_file {
+        ImageFile::Image(image) => write_image(args, image),
+        ImageFile::Gif(frames, _) if args.plays_in_terminal() => animate_gif(args, frames, &*terminal_draw(args)?),
+        ImageFile::Gif(frames, _) => match (args.configured_mode()?, args.animation_encoder()?) {
+            (Mode::Text(renderer), Some(encoder)) => {
+                let (width, height) = (args.options.width, args.options.height);
+                let frames: Vec<_> = frames.iter().map(|frame| Frame {
+                    grid: renderer.render(&frame.image, width, height),
+                    delay: frame.dalay_as_duration(),
+                }).collect();
+                write_animation(args, encoder.as_ref(), &frames)
+            },
+            // Other formats are still, so they get the first frame.
+            _ => match frames.first() {
+                Some(frame) => write_image(args, &frame.image),
+                None => Ok(()),
+            },
+        },
+    }
+}
 
-        if self.width > 0 {
-            self.height = (h/w*self.width as f32 / self.aspect) as u32;
-        }
-        else {
-            let (tw, th) = crossterm::terminal::size().unwrap_or((100, 100));
-            let (tw, th) = (tw as f32, th as f32);
-            let terminal_aspect = tw / th;
-            let image_aspect = w / h;
+/// Draws frames for playing them in the terminal with the configured mode.
+fn terminal_draw(args: &Args) -> Result<Box<Draw<'_>>> {
+    let (width, height) = (args.options.width, args.options.height);
+    let mode = args.configured_mode()?;
+    let encoder = args.encoder()?;
+    Ok(Box::new(move |image, buf| mode.draw_into(image, width, height, encoder.as_ref(), buf)))
+}
 
-            if terminal_aspect/self.aspect > image_aspect {
-                self.height = th as u32 - 2; // Leave some space for the prompt.
-                self.width = (w/h*self.height as f32 * self.aspect) as u32;
-            }
-            else {
-                self.width = tw as u32;
-                self.height = (h/w*self.width as f32 / self.aspect) as u32;
-            }
-        }
+/// Read a stream to its end, for writing it to a file or a pipe.
+fn collect(stream: Stream) -> Result<ImageFile> {
+    let delay = Delay::from_saturating_duration(stream.delay);
+    let frames = stream.frames
+        .map(|image| image.map(|image| GifFrame { image, delay }))
+        .collect::<Result<_>>()?;
+    Ok(ImageFile::Gif(frames, stream.dims))
+}
 
-        Ok(())
+/// Draw the image with the configured mode and write it to the output file or the terminal.
+fn write_image(args: &Args, image: &DynamicImage) -> Result<()> {
+    let mode = args.configured_mode()?;
+    let mut buf = Vec::new();
+    mode.draw_into(image, args.options.width, args.options.height, args.encoder()?.as_ref(), &mut buf).map_err(UniartError::Write)?;
+    // Graphics leave the cursor at the end of the image.
+    if let Mode::Graphics(_) = mode {
+        buf.push(b'\n');
     }
+    write_bytes(args, &buf)
+}
 
-    fn realize(&self) {
-        match &self.image_file {
-            Some(ImageFile::Image(image)) => {
-                // Just print the image.
-                (self.printer.unwrap())(self, image);
-            },
-            Some(ImageFile::Gif(frames, _)) => {
-                animate_gif(self, frames);
-            },
-            None => {
-                eprintln!("Error opening image file.");
-                exit(1);
-            }
-        }
+/// Encode all frames and write them to the output file or the terminal.
+fn write_animation(args: &Args, encoder: &dyn AnimationEncoder, frames: &[Frame]) -> Result<()> {
+    let mut buf = Vec::new();
+    encoder.encode_frames_into(frames, &mut buf).map_err(UniartError::Write)?;
+    write_bytes(args, &buf)
+}
+
+/// Write the output to the output file or the terminal at once.
+fn write_bytes(args: &Args, bytes: &[u8]) -> Result<()> {
+    match &args.output {
+        Some(path) => {
+            let mut file = File::create(path).map_err(UniartError::Write)?;
+            file.write_all(bytes).and_then(|_| file.flush()).map_err(UniartError::Write)
+        },
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(bytes).and_then(|_| stdout.flush()).map_err(UniartError::Terminal)
+        },
     }
 }
 
 fn main() {
     let mut args = Args::parse();
 
-    if let Err(e) = args.normalize() {
+    if let Err(e) = args.normalize().and_then(|_| realize(&mut args)) {
         eprintln!("{}", e);
-        return;
+        exit(e.exit_code());
     }
-    args.realize();
 }
@@ -0,0 +1,142 @@
+use std::io;
+
+use image::Rgba;
+
+use crate::{colors::{closest_in_palette, palette_codes, PaletteCodes}, encoder::{push_char, ColorDepth, Encoder}, grid::Grid};
+
+/// Colors 16 to 98 of the extended mIRC palette. Unlike 0 to 15, these look the same in every client.
+const MIRC_PALETTE: [[u8; 3]; 83] = [
+    [0x47, 0x00, 0x00], [0x47, 0x21, 0x00], [0x47, 0x47, 0x00], [0x32, 0x47, 0x00], [0x00, 0x47, 0x00], [0x00, 0x47, 0x2c],
+    [0x00, 0x47, 0x47], [0x00, 0x27, 0x47], [0x00, 0x00, 0x47], [0x2e, 0x00, 0x47], [0x47, 0x00, 0x47], [0x47, 0x00, 0x2a],
+    [0x74, 0x00, 0x00], [0x74, 0x3a, 0x00], [0x74, 0x74, 0x00], [0x51, 0x74, 0x00], [0x00, 0x74, 0x00], [0x00, 0x74, 0x49],
+    [0x00, 0x74, 0x74], [0x00, 0x40, 0x74], [0x00, 0x00, 0x74], [0x4b, 0x00, 0x74], [0x74, 0x00, 0x74], [0x74, 0x00, 0x45],
+    [0xb5, 0x00, 0x00], [0xb5, 0x63, 0x00], [0xb5, 0xb5, 0x00], [0x7d, 0xb5, 0x00], [0x00, 0xb5, 0x00], [0x00, 0xb5, 0x71],
+    [0x00, 0xb5, 0xb5], [0x00, 0x63, 0xb5], [0x00, 0x00, 0xb5], [0x75, 0x00, 0xb5], [0xb5, 0x00, 0xb5], [0xb5, 0x00, 0x6b],
+    [0xff, 0x00, 0x00], [0xff, 0x8c, 0x00], [0xff, 0xff, 0x00], [0xb2, 0xff, 0x00], [0x00, 0xff, 0x00], [0x00, 0xff, 0xa0],
+    [0x00, 0xff, 0xff], [0x00, 0x8c, 0xff], [0x00, 0x00, 0xff], [0xa5, 0x00, 0xff], [0xff, 0x00, 0xff], [0xff, 0x00, 0x98],
+    [0xff, 0x59, 0x59], [0xff, 0xb4, 0x59], [0xff, 0xff, 0x71], [0xcf, 0xff, 0x60], [0x6f, 0xff, 0x6f], [0x65, 0xff, 0xc9],
+    [0x6d, 0xff, 0xff], [0x59, 0xb4, 0xff], [0x59, 0x59, 0xff], [0xc4, 0x59, 0xff], [0xff, 0x66, 0xff], [0xff, 0x59, 0xbc],
+    [0xff, 0x9c, 0x9c], [0xff, 0xd3, 0x9c], [0xff, 0xff, 0x9c], [0xe2, 0xff, 0x9c], [0x9c, 0xff, 0x9c], [0x9c, 0xff, 0xdb],
+    [0x9c, 0xff, 0xff], [0x9c, 0xd3, 0xff], [0x9c, 0x9c, 0xff], [0xdc, 0x9c, 0xff], [0xff, 0x9c, 0xff], [0xff, 0x94, 0xd3],
+    [0x00, 0x00, 0x00], [0x13, 0x13, 0x13], [0x28, 0x28, 0x28], [0x36, 0x36, 0x36], [0x4d, 0x4d, 0x4d], [0x65, 0x65, 0x65],
+    [0x81, 0x81, 0x81], [0x9f, 0x9f, 0x9f], [0xbc, 0xbc, 0xbc], [0xe2, 0xe2, 0xe2], [0xff, 0xff, 0xff],
+];
+
+/// Code of the first color of [`MIRC_PALETTE`].
+const PALETTE_START: u8 = 16;
+
+/// The default color of the client.
+const DEFAULT: u8 = 99;
+
+/// Starts a color code.
+const COLOR: u8 = 0x03;
+
+/// Resets all formatting.
+const RESET: u8 = 0x0f;
+
+/// Emits the grid with mIRC color codes, for IRC clients. Every line can be sent as a message.
+pub struct MircEncoder {
+    pub depth: ColorDepth,
+}
+
+impl MircEncoder {
+    pub fn new(depth: ColorDepth) -> Self {
+        MircEncoder { depth }
+    }
+}
+
+/// The code of the closest color of the extended palette.
+fn mirc_code(col: Rgba<u8>) -> u8 {
+    PALETTE_START + closest_in_palette(col, &MIRC_PALETTE) as u8
+}
+
+/// Append a color code. Codes always have two digits, so that digits in the art aren't taken as part of them.
+fn push_code(buf: &mut Vec<u8>, fg: u8, bg: Option<u8>) {
+    buf.push(COLOR);
+    buf.extend_from_slice(&[b'0' + fg / 10, b'0' + fg % 10]);
+    if let Some(bg) = bg {
+        buf.extend_from_slice(&[b',', b'0' + bg / 10, b'0' + bg % 10]);
+    }
+}
+
+impl Encoder for MircEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        for row in grid.rows() {
+            // Colors as mIRC codes. `None` is the default of the client.
+            let mut state: PaletteCodes = (None, None);
+            for cell in row {
+                let (fg, bg) = palette_codes(cell, self.depth, state, mirc_code, mirc_code);
+
+                if (fg, bg) != state {
+                    // A background can't be unset on its own.
+                    if bg.is_none() && state.1.is_some() {
+                        buf.push(RESET);
+                    }
+                    // A comma right after a foreground code would start a background code.
+                    let explicit_bg = bg.or((cell.glyph == ',').then_some(DEFAULT));
+                    if fg.is_some() || explicit_bg.is_some() {
+                        push_code(buf, fg.unwrap_or(DEFAULT), explicit_bg);
+                    }
+                    state = (fg, bg);
+                }
+                push_char(buf, cell.glyph);
+            }
+            if state != (None, None) {
+                buf.push(RESET);
+            }
+            buf.push(b'\n');
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::Cell;
+
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn encode(cells: &[Cell]) -> Vec<u8> {
+        let mut grid = Grid::new(cells.len() as u32, 1);
+        for (x, cell) in cells.iter().enumerate() {
+            grid.set(x as u32, 0, *cell);
+        }
+        let mut buf = Vec::new();
+        MircEncoder::new(ColorDepth::Ansi256).encode_into(&grid, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn codes_have_two_digits() {
+        // Digits of the art follow the code, and the default color of the foreground is written as 99.
+        let buf = encode(&[Cell::new('7', RED, None), Cell::new(' ', RED, Some(Rgba([0, 0, 0, 255])))]);
+        assert_eq!(buf, b"\x03527\x0352,88 \x0f\n");
+        let buf = encode(&[Cell::new(' ', RED, Some(BLUE))]);
+        assert_eq!(buf, b"\x0399,60 \x0f\n");
+    }
+
+    #[test]
+    fn comma_after_a_code_gets_a_background() {
+        let buf = encode(&[Cell::new('#', BLUE, None), Cell::new(',', RED, None), Cell::new(',', RED, None)]);
+        assert_eq!(buf, b"\x0360#\x0352,99,,\x0f\n");
+    }
+
+    #[test]
+    fn background_is_unset_with_a_reset() {
+        let buf = encode(&[Cell::new('#', RED, Some(BLUE)), Cell::new('#', RED, None)]);
+        assert_eq!(buf, b"\x0352,60#\x0f\x0352#\x0f\n");
+    }
+
+    #[test]
+    fn monochrome_has_no_codes() {
+        let mut grid = Grid::new(2, 2);
+        grid.set(0, 0, Cell::new('#', RED, None));
+        let mut buf = Vec::new();
+        MircEncoder::new(ColorDepth::Monochrome).encode_into(&grid, &mut buf).unwrap();
+        assert_eq!(buf, b"# \n  \n");
+    }
+}
@@ -0,0 +1,22 @@
+use std::io;
+
+use crate::{encoder::AnimationEncoder, grid::Frame, json_encoder::Document};
+
+/// Emits the same data as [`JsonEncoder`](crate::JsonEncoder) as MessagePack, which is smaller and faster to parse.
+pub struct MsgpackEncoder {
+    /// Name of the mode that rendered the grids.
+    pub mode: String,
+}
+
+impl MsgpackEncoder {
+    pub fn new(mode: impl Into<String>) -> Self {
+        MsgpackEncoder { mode: mode.into() }
+    }
+}
+
+impl AnimationEncoder for MsgpackEncoder {
+    fn encode_frames_into(&self, frames: &[Frame], buf: &mut Vec<u8>) -> io::Result<()> {
+        // Field names are kept, so that the data is self-describing like the JSON.
+        rmp_serde::encode::write_named(buf, &Document::new(&self.mode, frames)).map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,162 @@
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+
+use crate::{encoder::ColorDepth, error::{Result, UniartError}};
+
+/// Describes how an image is rendered, independently of where the image comes from.
+///
+/// Can be built with [`RenderOptions::builder`] or deserialized, e.g. from a config file.
+/// Missing fields take their default values.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Name of the mode, or the beginning of it.
+    pub mode: String,
+    /// Width of the output in characters. If 0, it is derived from the height.
+    pub width: u32,
+    /// Height of the output in characters. If 0, it is derived from the width.
+    ///
+    /// If both are 0 the output fills the terminal window.
+    pub height: u32,
+    /// Aspect ratio of the terminal font.
+    pub aspect: f32,
+    pub color_depth: ColorDepth,
+    /// Sets the background color to black.
+    pub background: bool,
+    /// Inverts the image brightness.
+    pub invert: bool,
+    /// Switches the quality of the luminance, edges and shapes modes.
+    pub quality: bool,
+    /// Uses linear filter instead of nearest neighbor when scaling the image.
+    pub filter: bool,
+    /// Character palette of the shapes mode. `None` uses all printable ASCII characters.
+    pub palette: Option<String>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            mode: String::from("shapes"),
+            width: 0,
+            height: 0,
+            aspect: 2.0,
+            color_depth: ColorDepth::Monochrome,
+            background: false,
+            invert: false,
+            quality: false,
+            filter: false,
+            palette: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn builder() -> RenderOptionsBuilder {
+        RenderOptionsBuilder::default()
+    }
+
+    /// Check that the options are in range. Mode specific options are checked by the modes.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.aspect.is_finite() && self.aspect > 0.0) {
+            return Err(UniartError::InvalidOptions(format!("aspect must be a positive number, got {}", self.aspect)));
+        }
+        Ok(())
+    }
+
+    /// Filter used for scaling the image.
+    pub fn filter_type(&self) -> FilterType {
+        if self.filter {FilterType::Triangle} else {FilterType::Nearest}
+    }
+
+    /// Calculate the dimensions of the output in characters for an image of the given dimensions.
+    ///
+    /// If neither width nor height is set, the terminal size is used (or 100x100 if it cannot be determined).
+    /// Both dimensions are at least 1.
+    pub fn dimensions_for(&self, image_dimensions: (u32, u32)) -> (u32, u32) {
+        let (w, h) = (image_dimensions.0 as f32, image_dimensions.1 as f32);
+
+        let (width, height) = match (self.width, self.height) {
+            (0, 0) => {
+                let (tw, th) = crossterm::terminal::size().unwrap_or((100, 100));
+                let (tw, th) = (tw as f32, th as f32);
+                let terminal_aspect = tw / th;
+                let image_aspect = w / h;
+
+                if terminal_aspect/self.aspect > image_aspect {
+                    let height = (th as u32).saturating_sub(2); // Leave some space for the prompt.
+                    ((w/h*height as f32 * self.aspect) as u32, height)
+                }
+                else {
+                    let width = tw as u32;
+                    (width, (h/w*width as f32 / self.aspect) as u32)
+                }
+            },
+            (0, height) => ((w/h*height as f32 * self.aspect) as u32, height),
+            (width, 0) => (width, (h/w*width as f32 / self.aspect) as u32),
+            (width, height) => (width, height),
+        };
+        (width.max(1), height.max(1))
+    }
+}
+
+/// Builder for [`RenderOptions`], starting from the defaults.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptionsBuilder {
+    options: RenderOptions,
+}
+
+impl RenderOptionsBuilder {
+    pub fn mode(mut self, mode: impl Into<String>) -> Self {
+        self.options.mode = mode.into();
+        self
+    }
+
+    pub fn width(mut self, width: u32) -> Self {
+        self.options.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: u32) -> Self {
+        self.options.height = height;
+        self
+    }
+
+    pub fn aspect(mut self, aspect: f32) -> Self {
+        self.options.aspect = aspect;
+        self
+    }
+
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.options.color_depth = color_depth;
+        self
+    }
+
+    pub fn background(mut self, background: bool) -> Self {
+        self.options.background = background;
+        self
+    }
+
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.options.invert = invert;
+        self
+    }
+
+    pub fn quality(mut self, quality: bool) -> Self {
+        self.options.quality = quality;
+        self
+    }
+
+    pub fn filter(mut self, filter: bool) -> Self {
+        self.options.filter = filter;
+        self
+    }
+
+    pub fn palette(mut self, palette: impl Into<String>) -> Self {
+        self.options.palette = Some(palette.into());
+        self
+    }
+
+    pub fn build(self) -> RenderOptions {
+        self.options
+    }
+}
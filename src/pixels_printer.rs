@@ -1,42 +1,69 @@
-use image::{DynamicImage, GenericImageView};
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
 
-use crate::{colors::{reset_color, set_color, set_color_bg}, Args};
+use crate::{grid::{Cell, Grid}, options::RenderOptions, renderer::Renderer};
 
-pub fn print_pixels(args: &Args, image: &DynamicImage) {
-    let scaled = image.resize_exact(
-        args.width,
-        args.height,
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
+/// One colored block per pixel.
+pub struct PixelsRenderer {
+    filter: FilterType,
+}
+
+impl PixelsRenderer {
+    pub fn new(options: &RenderOptions) -> Self {
+        PixelsRenderer { filter: options.filter_type() }
+    }
+}
+
+impl Renderer for PixelsRenderer {
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid {
+        let scaled = image.resize_exact(width, height, self.filter);
 
-    for y in 0..scaled.height() {
-        for x in 0..scaled.width() {
-            let pixel = scaled.get_pixel(x, y);
+        let mut grid = Grid::new(scaled.width(), scaled.height());
+        for y in 0..scaled.height() {
+            for x in 0..scaled.width() {
+                let pixel = scaled.get_pixel(x, y);
 
-            set_color(pixel, args);
-            print!("█");
+                grid.set(x, y, Cell::new('█', pixel, None));
+            }
         }
-        reset_color();
-        println!()
+
+        grid
+    }
+
+    fn requires_colors(&self) -> bool {
+        true
     }
 }
 
-pub fn print_double_pixels(args: &Args, image: &DynamicImage) {
-    let scaled = image.resize_exact(
-        args.width,
-        args.height * 2, // each character is two pixels - top and bottom
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
-
-    for y in 0..args.height {
-        for x in 0..args.width {
-            let upper_px = scaled.get_pixel(x, 2*y);
-            let lower_px = scaled.get_pixel(x, 2*y+1);
-            
-            set_color_bg(lower_px, upper_px, args);
-            print!("▄");
+/// Two pixels per character, using the foreground and background colors of a half block.
+pub struct DoublePixelsRenderer {
+    filter: FilterType,
+}
+
+impl DoublePixelsRenderer {
+    pub fn new(options: &RenderOptions) -> Self {
+        DoublePixelsRenderer { filter: options.filter_type() }
+    }
+}
+
+impl Renderer for DoublePixelsRenderer {
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid {
+        // each character is two pixels - top and bottom
+        let scaled = image.resize_exact(width, height * 2, self.filter);
+
+        let mut grid = Grid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let upper_px = scaled.get_pixel(x, 2*y);
+                let lower_px = scaled.get_pixel(x, 2*y+1);
+
+                grid.set(x, y, Cell::new('▄', lower_px, Some(upper_px)));
+            }
         }
-        reset_color();
-        println!()
+
+        grid
+    }
+
+    fn requires_colors(&self) -> bool {
+        true
     }
-}
\ No newline at end of file
+}
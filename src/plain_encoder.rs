@@ -0,0 +1,39 @@
+use std::io;
+
+use crate::{encoder::{push_char, Encoder}, grid::Grid};
+
+/// Emits only the glyphs and line breaks, without any escape sequences.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlainEncoder {
+    /// Leave out whitespace at the end of lines.
+    pub trim: bool,
+}
+
+impl PlainEncoder {
+    pub fn new() -> Self {
+        PlainEncoder::default()
+    }
+
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+}
+
+impl Encoder for PlainEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        for row in grid.rows() {
+            let len = if self.trim {
+                row.iter().rposition(|cell| !cell.glyph.is_whitespace()).map_or(0, |i| i + 1)
+            }
+            else {
+                row.len()
+            };
+            for cell in &row[..len] {
+                push_char(buf, cell.glyph);
+            }
+            buf.push(b'\n');
+        }
+        Ok(())
+    }
+}
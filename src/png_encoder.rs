@@ -0,0 +1,27 @@
+use std::io::{self, Cursor};
+
+use image::ImageFormat;
+
+use crate::{encoder::Encoder, grid::Grid, rasterizer::Rasterizer};
+
+/// Emits a PNG image of the grid as a terminal would show it.
+pub struct PngEncoder {
+    pub rasterizer: Rasterizer,
+}
+
+impl PngEncoder {
+    pub fn new(rasterizer: Rasterizer) -> Self {
+        PngEncoder { rasterizer }
+    }
+}
+
+impl Encoder for PngEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        let image = self.rasterizer.rasterize(grid);
+        let mut cursor = Cursor::new(std::mem::take(buf));
+        cursor.set_position(cursor.get_ref().len() as u64);
+        let result = image.write_to(&mut cursor, ImageFormat::Png);
+        *buf = cursor.into_inner();
+        result.map_err(io::Error::other)
+    }
+}
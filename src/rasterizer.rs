@@ -0,0 +1,99 @@
+use image::{Rgb, RgbImage};
+
+use crate::{braille_printer::WEIGHTS, colors::{displayed_color, page_colors}, encoder::ColorDepth, error::Result, glyph_atlas::GlyphAtlas, grid::{Cell, Grid}};
+
+/// First braille character in unicode. The dots are the bits of the offset from it.
+const BRAILLE_START: u32 = 0x2800;
+
+/// Draws a grid into an image the way a terminal would show it, using the embedded glyph atlas.
+///
+/// Braille and block characters, which are not in the atlas, are drawn directly.
+/// Other characters that are not in the atlas are left blank.
+pub struct Rasterizer {
+    pub depth: ColorDepth,
+    atlas: GlyphAtlas,
+    /// Draw on a dark page, see [`page_colors`].
+    pub dark: bool,
+}
+
+impl Rasterizer {
+    /// Create a rasterizer with 8x16 pixels per character, or 4x8 if `small`.
+    pub fn new(depth: ColorDepth, small: bool) -> Result<Self> {
+        Ok(Rasterizer { depth, atlas: GlyphAtlas::load(small)?, dark: true })
+    }
+
+    pub fn with_dark(mut self, dark: bool) -> Self {
+        self.dark = dark;
+        self
+    }
+
+    /// Size of a character in pixels.
+    pub fn cell_size(&self) -> (u32, u32) {
+        (self.atlas.glyph_width, self.atlas.glyph_height)
+    }
+
+    /// Draw the grid, one cell per character.
+    pub fn rasterize(&self, grid: &Grid) -> RgbImage {
+        let (cw, ch) = self.cell_size();
+        let mut image = RgbImage::new(grid.width() * cw, grid.height() * ch);
+
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let cell = grid.get(x, y);
+                let (fg, bg) = self.colors(cell);
+                for v in 0..ch {
+                    for u in 0..cw {
+                        let coverage = self.coverage(cell.glyph, u, v);
+                        image.put_pixel(x*cw + u, y*ch + v, blend(bg, fg, coverage));
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    /// Foreground and background colors of a cell as displayed.
+    fn colors(&self, cell: &Cell) -> (Rgb<u8>, Rgb<u8>) {
+        let (page_fg, page_bg) = page_colors(self.dark);
+        let fg = match self.depth {
+            ColorDepth::Monochrome => page_fg,
+            depth => displayed_color(cell.fg, depth),
+        };
+        let bg = cell.bg.map(|bg| displayed_color(bg, self.depth)).unwrap_or(page_bg);
+        (fg, bg)
+    }
+
+    /// How much of the pixel (`u`, `v`) of a character is covered by the glyph.
+    fn coverage(&self, glyph: char, u: u32, v: u32) -> u8 {
+        let (cw, ch) = self.cell_size();
+        if let Some(index) = GlyphAtlas::index(glyph) {
+            return self.atlas.pixel(index, u, v);
+        }
+
+        match glyph {
+            '█' => 255,
+            '▄' if v >= ch / 2 => 255,
+            _ if (BRAILLE_START..BRAILLE_START + 256).contains(&(glyph as u32)) => {
+                let dots = (glyph as u32 - BRAILLE_START) as u8;
+                // Each dot is a square in the middle of its 2x4 part of the cell.
+                let (part_w, part_h) = (cw / 2, ch / 4);
+                let size = (cw / 4).max(1);
+                let (dx, du) = (u / part_w, u % part_w);
+                let (dy, dv) = (v / part_h, v % part_h);
+                let offset_u = (part_w - size) / 2;
+                let offset_v = (part_h - size) / 2;
+                let inside = (offset_u..offset_u + size).contains(&du) && (offset_v..offset_v + size).contains(&dv);
+                let set = dy < 4 && dx < 2 && dots & WEIGHTS[dy as usize][dx as usize] != 0;
+                if inside && set {255} else {0}
+            },
+            _ => 0,
+        }
+    }
+}
+
+/// Mix the background with the foreground by the given amount.
+fn blend(bg: Rgb<u8>, fg: Rgb<u8>, amount: u8) -> Rgb<u8> {
+    let a = amount as u32;
+    Rgb(std::array::from_fn(|i| ((bg[i] as u32 * (255 - a) + fg[i] as u32 * a) / 255) as u8))
+}
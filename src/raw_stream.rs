@@ -0,0 +1,140 @@
+use std::{io::{self, Read}, str::FromStr};
+
+use image::{DynamicImage, RgbImage, RgbaImage};
+
+use crate::error::{Result, UniartError};
+
+/// Order of the bytes of a pixel in a raw frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelLayout {
+    Rgb,
+    Rgba,
+}
+
+impl PixelLayout {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelLayout::Rgb => 3,
+            PixelLayout::Rgba => 4,
+        }
+    }
+}
+
+/// Size and layout of the frames of a raw stream, written as `WxH:rgb` or `WxH:rgba`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawFormat {
+    pub width: u32,
+    pub height: u32,
+    pub layout: PixelLayout,
+}
+
+impl RawFormat {
+    /// Number of bytes of one frame.
+    pub fn frame_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.layout.bytes_per_pixel()
+    }
+}
+
+impl FromStr for RawFormat {
+    type Err = UniartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || UniartError::InvalidOptions(format!("raw format must look like 640x480:rgba, got {s}"));
+
+        let (size, layout) = s.split_once(':').ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let layout = match layout {
+            "rgb" => PixelLayout::Rgb,
+            "rgba" => PixelLayout::Rgba,
+            _ => return Err(invalid()),
+        };
+        let width: u32 = width.parse().map_err(|_| invalid())?;
+        let height: u32 = height.parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+
+        Ok(RawFormat { width, height, layout })
+    }
+}
+
+/// Reads fixed-size raw frames from a stream as they arrive, e.g. from stdin or a FIFO.
+///
+/// The stream ends at the end of the input. A truncated last frame is dropped.
+pub struct RawFrames<R> {
+    reader: R,
+    format: RawFormat,
+    done: bool,
+}
+
+impl<R: Read> RawFrames<R> {
+    pub fn new(reader: R, format: RawFormat) -> Self {
+        RawFrames { reader, format, done: false }
+    }
+}
+
+impl<R: Read> Iterator for RawFrames<R> {
+    type Item = Result<DynamicImage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut bytes = vec![0; self.format.frame_size()];
+        if let Err(e) = self.reader.read_exact(&mut bytes) {
+            self.done = true;
+            return match e.kind() {
+                io::ErrorKind::UnexpectedEof => None,
+                _ => Some(Err(e.into())),
+            };
+        }
+
+        // The buffer has exactly the size of a frame, so creating the image can't fail.
+        let RawFormat { width, height, layout } = self.format;
+        let image = match layout {
+            PixelLayout::Rgb => RgbImage::from_raw(width, height, bytes).map(DynamicImage::ImageRgb8),
+            PixelLayout::Rgba => RgbaImage::from_raw(width, height, bytes).map(DynamicImage::ImageRgba8),
+        };
+        image.map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn format_from_str() {
+        assert_eq!("640x480:rgba".parse::<RawFormat>().unwrap(), RawFormat { width: 640, height: 480, layout: PixelLayout::Rgba });
+        assert_eq!("3x2:rgb".parse::<RawFormat>().unwrap(), RawFormat { width: 3, height: 2, layout: PixelLayout::Rgb });
+        assert_eq!("3x2:rgb".parse::<RawFormat>().unwrap().frame_size(), 18);
+    }
+
+    #[test]
+    fn format_from_str_rejects_invalid_formats() {
+        for s in ["640x480", "640:rgba", "640x480:bgr", "0x480:rgba", "640x0:rgb", "-1x2:rgb", "axb:rgb", "640x480:RGBA", ""] {
+            assert!(matches!(s.parse::<RawFormat>(), Err(UniartError::InvalidOptions(_))), "{s}");
+        }
+    }
+
+    #[test]
+    fn frames_are_read_in_order() {
+        let format = RawFormat { width: 2, height: 1, layout: PixelLayout::Rgb };
+        let frames: Vec<_> = RawFrames::new(Cursor::new((0..12).collect::<Vec<u8>>()), format)
+            .map(|frame| frame.unwrap().to_rgb8().into_raw())
+            .collect();
+        assert_eq!(frames, [vec![0, 1, 2, 3, 4, 5], vec![6, 7, 8, 9, 10, 11]]);
+    }
+
+    #[test]
+    fn truncated_last_frame_is_dropped() {
+        let format = RawFormat { width: 1, height: 1, layout: PixelLayout::Rgba };
+        let mut frames = RawFrames::new(Cursor::new(vec![1, 2, 3, 4, 5, 6]), format);
+        assert_eq!(frames.next().unwrap().unwrap().to_rgba8().into_raw(), [1, 2, 3, 4]);
+        assert!(frames.next().is_none());
+        assert!(frames.next().is_none());
+    }
+}
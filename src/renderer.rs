@@ -0,0 +1,170 @@
+use std::io;
+
+use image::DynamicImage;
+
+use crate::{braille_printer::BrailleRenderer, encoder::{ColorDepth, Encoder}, error::{Result, UniartError}, edges_printer::EdgesRenderer, graphics::{GraphicsEncoder, Scaling}, grid::Grid, iterm_encoder::ItermEncoder, kitty_encoder::KittyEncoder, luminance_printer::LuminanceRenderer, pixels_printer::{DoublePixelsRenderer, PixelsRenderer}, options::RenderOptions, shape_printer::ShapesRenderer, sixel_encoder::SixelEncoder};
+
+/// A mode of turning images into characters.
+///
+/// Renderers own their configuration and any state that can be prepared once and reused between frames.
+pub trait Renderer {
+    /// Render an image into a grid of `width`x`height` cells.
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid;
+
+    /// Whether the output of this mode only makes sense in color.
+    fn requires_colors(&self) -> bool {
+        false
+    }
+}
+
+/// Creates a renderer configured by the options, or fails if the options don't suit the mode.
+pub type RendererFactory = fn(&RenderOptions) -> Result<Box<dyn Renderer>>;
+
+/// Creates a graphics encoder configured by the options, or fails if the options don't suit the mode.
+pub type GraphicsFactory = fn(&RenderOptions) -> Result<Box<dyn GraphicsEncoder>>;
+
+/// How a registered mode is created.
+#[derive(Clone, Copy)]
+pub enum ModeFactory {
+    /// The mode renders images into grids of cells.
+    Text(RendererFactory),
+    /// The mode draws images with pixels through a terminal graphics protocol.
+    Graphics(GraphicsFactory),
+}
+
+/// A mode configured by the options, ready to draw images.
+pub enum Mode {
+    Text(Box<dyn Renderer>),
+    Graphics(Box<dyn GraphicsEncoder>),
+}
+
+impl Mode {
+    /// Append the output that draws the image over `width`x`height` cells.
+    ///
+    /// Text modes render a grid and encode it with `encoder`, graphics modes write the escape sequences of their protocol.
+    pub fn draw_into(&self, image: &DynamicImage, width: u32, height: u32, encoder: &dyn Encoder, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Mode::Text(renderer) => encoder.encode_into(&renderer.render(image, width, height), buf),
+            Mode::Graphics(graphics) => graphics.encode_image_into(image, width, height, buf),
+        }
+    }
+}
+
+/// A set of named modes.
+pub struct Registry {
+    modes: Vec<(String, ModeFactory)>,
+}
+
+impl Registry {
+    /// Create a registry without any modes.
+    pub fn empty() -> Self {
+        Registry { modes: Vec::new() }
+    }
+
+    /// Add a mode that renders into grids, replacing any existing mode with the same name.
+    pub fn register(&mut self, name: impl Into<String>, factory: RendererFactory) {
+        self.insert(name.into(), ModeFactory::Text(factory));
+    }
+
+    /// Add a mode that draws with a graphics protocol, replacing any existing mode with the same name.
+    pub fn register_graphics(&mut self, name: impl Into<String>, factory: GraphicsFactory) {
+        self.insert(name.into(), ModeFactory::Graphics(factory));
+    }
+
+    fn insert(&mut self, name: String, factory: ModeFactory) {
+        match self.modes.iter_mut().find(|(n, _)| *n == name) {
+            Some(mode) => mode.1 = factory,
+            None => self.modes.push((name, factory)),
+        }
+    }
+
+    /// Find a mode by its name, or by the beginning of its name.
+    ///
+    /// Returns the full name of the mode along with its factory.
+    pub fn find(&self, name: &str) -> Option<(&str, ModeFactory)> {
+        self.modes.iter().find(|(n, _)| n == name)
+            .or_else(|| self.modes.iter().find(|(n, _)| n.starts_with(name)))
+            .map(|(n, f)| (n.as_str(), *f))
+    }
+
+    /// Create the mode selected in the options.
+    pub fn create_mode(&self, options: &RenderOptions) -> Result<Mode> {
+        options.validate()?;
+        match self.find(&options.mode) {
+            Some((_, ModeFactory::Text(factory))) => factory(options).map(Mode::Text),
+            Some((_, ModeFactory::Graphics(factory))) => factory(options).map(Mode::Graphics),
+            None => Err(UniartError::InvalidMode(options.mode.clone())),
+        }
+    }
+
+    /// Create the mode selected in the options and adjust the options to it.
+    ///
+    /// Modes that only make sense in color get 256 colors instead of monochrome.
+    pub fn configure(&self, options: &mut RenderOptions) -> Result<Mode> {
+        let mode = self.create_mode(options)?;
+        if let Mode::Text(renderer) = &mode {
+            if renderer.requires_colors() && options.color_depth == ColorDepth::Monochrome {
+                options.color_depth = ColorDepth::Ansi256;
+            }
+        }
+        Ok(mode)
+    }
+
+    /// Create the renderer of the mode selected in the options. Fails for graphics modes, which don't render grids.
+    pub fn create(&self, options: &RenderOptions) -> Result<Box<dyn Renderer>> {
+        match self.create_mode(options)? {
+            Mode::Text(renderer) => Ok(renderer),
+            Mode::Graphics(_) => Err(UniartError::InvalidOptions(format!("the {} mode draws pixels, not a grid of cells", options.mode))),
+        }
+    }
+
+    /// Names of the registered modes, in the order of registration.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.modes.iter().map(|(n, _)| n.as_str())
+    }
+}
+
+impl Default for Registry {
+    /// Registry with all the built-in modes.
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register("luminance",     |options| Ok(Box::new(LuminanceRenderer::new(options))));
+        registry.register("pixels",        |options| Ok(Box::new(PixelsRenderer::new(options))));
+        registry.register("double-pixels", |options| Ok(Box::new(DoublePixelsRenderer::new(options))));
+        registry.register("braille",       |options| Ok(Box::new(BrailleRenderer::new(options))));
+        registry.register("edges",         |options| Ok(Box::new(EdgesRenderer::new(options))));
+        registry.register("shapes",        |options| Ok(Box::new(ShapesRenderer::new(options)?)));
+        registry.register_graphics("sixel", |options| Ok(Box::new(SixelEncoder::new(Scaling::for_options(options)))));
+        registry.register_graphics("kitty", |options| Ok(Box::new(KittyEncoder::new(Scaling::for_options(options)))));
+        registry.register_graphics("iterm", |options| Ok(Box::new(ItermEncoder::new(Scaling::for_options(options)))));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured(mode: &str, color_depth: ColorDepth) -> ColorDepth {
+        let mut options = RenderOptions::builder().mode(mode).color_depth(color_depth).build();
+        Registry::default().configure(&mut options).unwrap();
+        options.color_depth
+    }
+
+    #[test]
+    fn configure_turns_on_colors_for_modes_that_need_them() {
+        assert_eq!(configured("pixels", ColorDepth::Monochrome), ColorDepth::Ansi256);
+        assert_eq!(configured("double-pixels", ColorDepth::Monochrome), ColorDepth::Ansi256);
+        assert_eq!(configured("pixels", ColorDepth::TrueColor), ColorDepth::TrueColor);
+        assert_eq!(configured("shapes", ColorDepth::Monochrome), ColorDepth::Monochrome);
+        assert_eq!(configured("sixel", ColorDepth::Monochrome), ColorDepth::Monochrome);
+    }
+
+    #[test]
+    fn draw_uses_the_configured_colors() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0])));
+        let options = RenderOptions::builder().mode("pixels").width(2).height(1).build();
+        let output = crate::draw(&image, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "\x1b[38;5;196m██\x1b[0m\n");
+    }
+}
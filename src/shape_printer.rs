@@ -1,73 +1,82 @@
-use image::{DynamicImage, GenericImageView};
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgba};
 
-use crate::{colors::{reset_color, set_black_background, set_color}, Args};
+use crate::{colors::background, error::{Result, UniartError}, glyph_atlas::GlyphAtlas, grid::{Cell, Grid}, options::RenderOptions, renderer::Renderer};
 
-pub const SMALL_SYMBOLS_FILE: &[u8] = include_bytes!("../assets/symbols_small.png");
-const SMALL_SYMBOLS_WIDTH: u32 = 4;
-const SMALL_SYMBOLS_HEIGHT: u32 = 8;
+/// Characters used when no palette is given.
+const DEFAULT_PALETTE: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
 
-pub const BIG_SYMBOLS_FILE: &[u8] = include_bytes!("../assets/symbols_big.png");
-const BIG_SYMBOLS_WIDTH: u32 = 8;
-const BIG_SYMBOLS_HEIGHT: u32 = 16;
+/// Selects characters with a shape similar to the represented area of the image.
+pub struct ShapesRenderer {
+    symbols: GlyphAtlas,
+    /// Indices of the palette characters in the atlas.
+    palette: Vec<u32>,
+    invert: bool,
+    filter: FilterType,
+    background: Option<Rgba<u8>>,
+}
 
-pub const ASCII_START: u32 = 32;
-pub const ASCII_END: u32 = 127;
+impl ShapesRenderer {
+    /// Create the renderer. Fails if the palette contains characters other than printable ASCII.
+    pub fn new(options: &RenderOptions) -> Result<Self> {
+        let palette = options.palette.as_deref().unwrap_or(DEFAULT_PALETTE);
+        let palette = palette.chars()
+            .map(|c| GlyphAtlas::index(c).ok_or(UniartError::InvalidPalette(c)))
+            .collect::<Result<_>>()?;
 
-pub fn print_shapes(args: &Args, image: &DynamicImage) {
-    let symbols_width =  if args.quality {SMALL_SYMBOLS_WIDTH}  else {BIG_SYMBOLS_WIDTH};
-    let symbols_height = if args.quality {SMALL_SYMBOLS_HEIGHT} else {BIG_SYMBOLS_HEIGHT};
+        Ok(ShapesRenderer {
+            symbols: GlyphAtlas::load(options.quality)?,
+            palette,
+            invert: options.invert,
+            filter: options.filter_type(),
+            background: background(options),
+        })
+    }
+}
+
+impl Renderer for ShapesRenderer {
+    fn render(&self, image: &DynamicImage, width: u32, height: u32) -> Grid {
+        let (symbols_width, symbols_height) = (self.symbols.glyph_width, self.symbols.glyph_height);
 
-    let symbols = args.shapes_symbols.as_ref().unwrap();
-    
-    // One pixel per character.
-    let colors = image.resize_exact(
-        args.width,
-        args.height, 
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
+        // One pixel per character.
+        let colors = image.resize_exact(width, height, self.filter);
 
-    // WxH pixels per character, according to the selected quality.
-    let image = image.resize_exact(
-        args.width * symbols_width,
-        args.height * symbols_height, 
-        if args.filter {image::imageops::FilterType::Triangle} else {image::imageops::FilterType::Nearest}
-    );
-    let image = image.to_luma8();
+        // WxH pixels per character, according to the selected quality.
+        let image = image.resize_exact(width * symbols_width, height * symbols_height, self.filter);
+        let image = image.to_luma8();
 
-    for y in 0..args.height {
-        set_black_background(args);
-        for x in 0..args.width {
-            let x0 = x * symbols_width;
-            let y0 = y * symbols_height;
+        let mut grid = Grid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = x * symbols_width;
+                let y0 = y * symbols_height;
 
-            // select the best symbol for the current area
-            let mut best_symbol = 0;
-            let mut best_cost = u32::MAX;
+                // select the best symbol for the current area
+                let mut best_symbol = 0;
+                let mut best_cost = u32::MAX;
 
-            for c in args.palette.as_ref().unwrap().chars() {
-                let symbol = c as u32 - ASCII_START;
-                let mut symbol_cost = 0;
-                for v in 0..symbols_height {
-                    for u in 0..symbols_width {
-                        let symbol_luminance = symbols.get_pixel(symbol*symbols_width+u, v).0[0];
-                        let mut image_luminance = image.get_pixel(x0 + u, y0 + v).0[0];
-                        if args.invert { image_luminance = 255 - image_luminance; }
-                        let pixel_difference = (symbol_luminance as i32 - image_luminance as i32).abs() as u32;
-                        symbol_cost += pixel_difference;
+                for &symbol in &self.palette {
+                    let mut symbol_cost = 0;
+                    for v in 0..symbols_height {
+                        for u in 0..symbols_width {
+                            let symbol_luminance = self.symbols.pixel(symbol, u, v);
+                            let mut image_luminance = image.get_pixel(x0 + u, y0 + v).0[0];
+                            if self.invert { image_luminance = 255 - image_luminance; }
+                            let pixel_difference = symbol_luminance.abs_diff(image_luminance) as u32;
+                            symbol_cost += pixel_difference;
+                        }
+                    }
+                    if symbol_cost < best_cost {
+                        best_cost = symbol_cost;
+                        best_symbol = symbol;
                     }
                 }
-                if symbol_cost < best_cost {
-                    best_cost = symbol_cost;
-                    best_symbol = symbol;
-                }
-            }
 
-            let symbol = std::char::from_u32(ASCII_START + best_symbol).unwrap();
+                let symbol = GlyphAtlas::char(best_symbol);
 
-            set_color(colors.get_pixel(x, y), args);
-            print!("{}", symbol);
+                grid.set(x, y, Cell::new(symbol, colors.get_pixel(x, y), self.background));
+            }
         }
-        reset_color();
-        println!();
+
+        grid
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,289 @@
+use std::io;
+
+use color_quant::NeuQuant;
+use image::DynamicImage;
+
+use crate::{encoder::{push_u32, push_u8}, graphics::{GraphicsEncoder, Scaling}};
+
+/// Pixels with lower alpha are left transparent.
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Draws the image as sixels, with a palette quantized from the image.
+///
+/// Supported by e.g. xterm, foot, mlterm and WezTerm.
+pub struct SixelEncoder {
+    pub scaling: Scaling,
+    /// Number of colors in the palette, at most 256.
+    pub colors: usize,
+}
+
+impl SixelEncoder {
+    pub fn new(scaling: Scaling) -> Self {
+        SixelEncoder { scaling, colors: 256 }
+    }
+
+    pub fn with_colors(mut self, colors: usize) -> Self {
+        self.colors = colors.clamp(2, 256);
+        self
+    }
+}
+
+/// Append a sixel `count` times, run length encoded if that's shorter.
+fn push_run(buf: &mut Vec<u8>, sixel: u8, count: u32) {
+    if count > 3 {
+        buf.push(b'!');
+        push_u32(buf, count);
+        buf.push(sixel);
+    }
+    else {
+        buf.extend((0..count).map(|_| sixel));
+    }
+}
+
+impl GraphicsEncoder for SixelEncoder {
+    fn encode_image_into(&self, image: &DynamicImage, width: u32, height: u32, buf: &mut Vec<u8>) -> io::Result<()> {
+        let image = self.scaling.resize(image, width, height);
+        let (w, h) = image.dimensions();
+
+        // Quantize only the visible pixels, as if they were opaque.
+        let opaque: Vec<u8> = image.pixels().filter(|p| p[3] >= ALPHA_THRESHOLD).flat_map(|p| [p[0], p[1], p[2], 255]).collect();
+        let quantizer = NeuQuant::new(10, self.colors, if opaque.is_empty() {&[0, 0, 0, 255]} else {&opaque});
+        let mut indices: Vec<Option<u8>> = image.pixels()
+            .map(|p| (p[3] >= ALPHA_THRESHOLD).then(|| quantizer.index_of(&[p[0], p[1], p[2], 255]) as u8))
+            .collect();
+
+        // Only the colors that are used get a register, numbered in the order of the quantized palette.
+        let palette = quantizer.color_map_rgb();
+        let mut registers = [None; 256];
+        for &index in indices.iter().flatten() {
+            registers[index as usize] = Some(0);
+        }
+        let mut colors = Vec::new();
+        for (index, register) in registers.iter_mut().enumerate() {
+            if register.is_some() {
+                *register = Some(colors.len() as u8);
+                colors.push(&palette[index * 3..index * 3 + 3]);
+            }
+        }
+        for index in indices.iter_mut().flatten() {
+            *index = registers[*index as usize].unwrap_or(0);
+        }
+
+        // Start the sixel data with transparent background and a raster of 1:1 pixels of the given size.
+        buf.extend_from_slice(b"\x1bP0;1;0q\"1;1;");
+        push_u32(buf, w);
+        buf.push(b';');
+        push_u32(buf, h);
+
+        for (i, rgb) in colors.iter().enumerate() {
+            buf.push(b'#');
+            push_u8(buf, i as u8);
+            buf.extend_from_slice(b";2");
+            for &c in rgb.iter() {
+                // Channels are in percent.
+                buf.push(b';');
+                push_u8(buf, ((c as u32 * 100 + 127) / 255) as u8);
+            }
+        }
+
+        // Each band is 6 pixels high and drawn color by color, from the bits of every color in every column.
+        let colors = colors.len();
+        let mut used = vec![false; colors];
+        let mut masks = vec![0u8; colors * w as usize];
+        for band in (0..h).step_by(6) {
+            for y in band..(band + 6).min(h) {
+                for x in 0..w {
+                    if let Some(color) = indices[(y * w + x) as usize] {
+                        used[color as usize] = true;
+                        masks[color as usize * w as usize + x as usize] |= 1 << (y - band);
+                    }
+                }
+            }
+
+            let mut first = true;
+            for color in 0..colors {
+                if !used[color] {
+                    continue;
+                }
+                if !first {
+                    // Back to the start of the band.
+                    buf.push(b'$');
+                }
+                first = false;
+                buf.push(b'#');
+                push_u8(buf, color as u8);
+
+                let mask = &mut masks[color * w as usize..(color + 1) * w as usize];
+                let mut run = (b'?', 0);
+                for bits in mask.iter_mut() {
+                    let sixel = b'?' + *bits;
+                    if sixel != run.0 {
+                        push_run(buf, run.0, run.1);
+                        run = (sixel, 0);
+                    }
+                    run.1 += 1;
+                    *bits = 0;
+                }
+                // Empty sixels at the end of the line can be left out.
+                if run.0 != b'?' {
+                    push_run(buf, run.0, run.1);
+                }
+                used[color] = false;
+            }
+            buf.push(b'-');
+        }
+
+        buf.extend_from_slice(b"\x1b\\");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    /// The parts of decoded sixel output.
+    struct Sixel {
+        /// Size from the raster attributes.
+        size: (u32, u32),
+        /// Colors of the registers, in percent.
+        palette: HashMap<u8, [u8; 3]>,
+        /// Register of every pixel, `None` where nothing was drawn.
+        pixels: Vec<Option<u8>>,
+    }
+
+    fn number(data: &[u8], i: &mut usize) -> u32 {
+        let start = *i;
+        while data[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        std::str::from_utf8(&data[start..*i]).unwrap().parse().unwrap()
+    }
+
+    fn decode(data: &[u8]) -> Sixel {
+        let body = data.strip_prefix(b"\x1bP0;1;0q\"1;1;").expect("header").strip_suffix(b"\x1b\\").expect("terminator");
+        let mut i = 0;
+        let width = number(body, &mut i);
+        assert_eq!(body[i], b';');
+        i += 1;
+        let height = number(body, &mut i);
+
+        let mut sixel = Sixel { size: (width, height), palette: HashMap::new(), pixels: vec![None; (width * height) as usize] };
+        let (mut x, mut band, mut register) = (0, 0, 0);
+        while i < body.len() {
+            let mut count = 1;
+            match body[i] {
+                b'#' => {
+                    i += 1;
+                    register = number(body, &mut i) as u8;
+                    if body.get(i) == Some(&b';') {
+                        assert_eq!(&body[i..i + 3], b";2;");
+                        i += 3;
+                        let mut rgb = [0; 3];
+                        for (c, channel) in rgb.iter_mut().enumerate() {
+                            *channel = number(body, &mut i) as u8;
+                            if c < 2 {
+                                i += 1;
+                            }
+                        }
+                        sixel.palette.insert(register, rgb);
+                    }
+                    continue;
+                },
+                b'$' => x = 0,
+                b'-' => (x, band) = (0, band + 1),
+                b'!' => {
+                    i += 1;
+                    count = number(body, &mut i);
+                },
+                _ => (),
+            }
+            if matches!(body[i], b'?'..=b'~') {
+                let bits = body[i] - b'?';
+                for _ in 0..count {
+                    for bit in 0..6 {
+                        let y = band * 6 + bit;
+                        if bits & (1 << bit) != 0 && y < height {
+                            let pixel = &mut sixel.pixels[(y * width + x) as usize];
+                            assert_eq!(*pixel, None, "pixel {x},{y} drawn twice");
+                            *pixel = Some(register);
+                        }
+                    }
+                    x += 1;
+                }
+            }
+            i += 1;
+        }
+        sixel
+    }
+
+    /// Encode the image with one pixel per cell, so that it isn't scaled.
+    fn encode(image: RgbaImage) -> Vec<u8> {
+        let (width, height) = image.dimensions();
+        let mut buf = Vec::new();
+        SixelEncoder::new(Scaling::new((1, 1))).encode_image_into(&DynamicImage::ImageRgba8(image), width, height, &mut buf).unwrap();
+        buf
+    }
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const TRANSPARENT: Rgba<u8> = Rgba([0, 255, 0, 0]);
+
+    #[test]
+    fn raster_attributes_give_the_size_in_pixels() {
+        let mut buf = Vec::new();
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 3, RED));
+        SixelEncoder::new(Scaling::new((4, 8))).encode_image_into(&image, 5, 2, &mut buf).unwrap();
+        assert!(buf.starts_with(b"\x1bP0;1;0q\"1;1;20;16#"));
+        assert_eq!(decode(&buf).size, (20, 16));
+    }
+
+    #[test]
+    fn palette_has_only_the_used_colors() {
+        let sixel = decode(&encode(RgbaImage::from_pixel(4, 4, RED)));
+        assert_eq!(sixel.palette.into_values().collect::<Vec<_>>(), [[100, 0, 0]]);
+
+        let sixel = decode(&encode(RgbaImage::from_fn(4, 4, |x, _| if x < 2 {RED} else {BLUE})));
+        assert_eq!(sixel.palette.len(), 2);
+    }
+
+    #[test]
+    fn bands_draw_every_pixel_in_its_color() {
+        // 8 rows, so that the second band is cut off.
+        let image = RgbaImage::from_fn(7, 8, |x, y| if (x + y) % 3 == 0 {RED} else {BLUE});
+        let sixel = decode(&encode(image.clone()));
+        let register = |color| image.pixels().position(|pixel| *pixel == color).map(|i| sixel.pixels[i].expect("drawn pixel"));
+        let (red, blue) = (register(RED), register(BLUE));
+        assert_ne!(red, blue);
+        for (pixel, register) in image.pixels().zip(&sixel.pixels) {
+            assert_eq!(*register, if *pixel == RED {red} else {blue});
+        }
+    }
+
+    #[test]
+    fn runs_are_length_encoded() {
+        let buf = encode(RgbaImage::from_pixel(10, 6, RED));
+        assert!(buf.ends_with(b"#0!10~-\x1b\\"), "{:?}", String::from_utf8_lossy(&buf));
+
+        // Runs of up to 3 sixels are shorter without the count.
+        let buf = encode(RgbaImage::from_pixel(3, 6, RED));
+        assert!(buf.ends_with(b"#0~~~-\x1b\\"), "{:?}", String::from_utf8_lossy(&buf));
+    }
+
+    #[test]
+    fn transparent_pixels_are_not_drawn() {
+        let image = RgbaImage::from_fn(6, 6, |x, y| if x == y || x == 5 {TRANSPARENT} else {RED});
+        let buf = encode(image.clone());
+        let sixel = decode(&buf);
+        assert_eq!(sixel.palette.len(), 1);
+        for (pixel, register) in image.pixels().zip(&sixel.pixels) {
+            assert_eq!(register.is_some(), *pixel == RED);
+        }
+        // The transparent column at the end of the line is left out.
+        assert!(!buf.windows(2).any(|w| w == b"?-"));
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Write};
+
+use image::Rgb;
+
+use crate::{colors::{displayed_color, page_colors, to_hex}, encoder::{push_xml_escaped, ColorDepth, Encoder}, grid::{Cell, Grid}};
+
+/// Emits an SVG image with every cell laid out on a fixed grid.
+///
+/// Full and lower half blocks (used by the pixels modes) are drawn as rectangles, everything else as text.
+pub struct SvgEncoder {
+    pub depth: ColorDepth,
+    pub font_family: String,
+    /// Width of a cell in pixels.
+    pub cell_width: f32,
+    /// Height of a cell in pixels, usually the width multiplied by the aspect ratio of the font.
+    pub cell_height: f32,
+    /// Draw on a dark page, see [`page_colors`].
+    pub dark: bool,
+}
+
+impl SvgEncoder {
+    /// Create an encoder with cells of the given width and aspect ratio.
+    pub fn new(depth: ColorDepth, cell_width: f32, aspect: f32) -> Self {
+        SvgEncoder {
+            depth,
+            font_family: String::from("monospace"),
+            cell_width,
+            cell_height: cell_width * aspect,
+            dark: true,
+        }
+    }
+
+    pub fn with_font_family(mut self, font_family: impl Into<String>) -> Self {
+        self.font_family = font_family.into();
+        self
+    }
+
+    pub fn with_dark(mut self, dark: bool) -> Self {
+        self.dark = dark;
+        self
+    }
+
+    fn fg(&self, cell: &Cell) -> Rgb<u8> {
+        match self.depth {
+            ColorDepth::Monochrome => page_colors(self.dark).0,
+            depth => displayed_color(cell.fg, depth),
+        }
+    }
+
+    /// Write a rectangle covering `len` cells from (`x`, `y`), spanning the given fraction of the cell height.
+    fn rect(&self, buf: &mut Vec<u8>, x: usize, y: usize, len: usize, (top, bottom): (f32, f32), col: Rgb<u8>) -> io::Result<()> {
+        writeln!(
+            buf,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            x as f32 * self.cell_width,
+            (y as f32 + top) * self.cell_height,
+            len as f32 * self.cell_width,
+            (bottom - top) * self.cell_height,
+            to_hex(col),
+        )
+    }
+
+    /// Write the glyphs of `cells` as a single text element, stretched to the width of the cells.
+    fn text(&self, buf: &mut Vec<u8>, x: usize, y: usize, cells: &[Cell], col: Rgb<u8>) -> io::Result<()> {
+        write!(
+            buf,
+            "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" fill=\"{}\">",
+            x as f32 * self.cell_width,
+            (y as f32 + 0.8) * self.cell_height,
+            cells.len() as f32 * self.cell_width,
+            to_hex(col),
+        )?;
+        for cell in cells {
+            push_xml_escaped(buf, cell.glyph);
+        }
+        buf.extend_from_slice(b"</text>\n");
+        Ok(())
+    }
+}
+
+/// Vertical extent of the block glyphs as fractions of the cell height.
+fn block_extent(glyph: char) -> Option<(f32, f32)> {
+    match glyph {
+        '█' => Some((0.0, 1.0)),
+        '▄' => Some((0.5, 1.0)),
+        _ => None,
+    }
+}
+
+/// Call `f` with every run of consecutive cells for which `key` returns the same `Some` value.
+fn for_each_run<K: PartialEq>(row: &[Cell], key: impl Fn(&Cell) -> Option<K>, mut f: impl FnMut(usize, &[Cell], K) -> io::Result<()>) -> io::Result<()> {
+    let mut start = 0;
+    while start < row.len() {
+        let Some(k) = key(&row[start]) else {
+            start += 1;
+            continue;
+        };
+        let mut end = start + 1;
+        while end < row.len() && key(&row[end]).as_ref() == Some(&k) {
+            end += 1;
+        }
+        f(start, &row[start..end], k)?;
+        start = end;
+    }
+    Ok(())
+}
+
+impl Encoder for SvgEncoder {
+    fn encode_into(&self, grid: &Grid, buf: &mut Vec<u8>) -> io::Result<()> {
+        let width = grid.width() as f32 * self.cell_width;
+        let height = grid.height() as f32 * self.cell_height;
+        let (_, page_bg) = page_colors(self.dark);
+
+        writeln!(buf, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">")?;
+        writeln!(buf, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", to_hex(page_bg))?;
+        write!(buf, "<g font-family=\"")?;
+        for c in self.font_family.chars() {
+            push_xml_escaped(buf, c);
+        }
+        writeln!(buf, "\" font-size=\"{}\" xml:space=\"preserve\">", self.cell_height * 0.8)?;
+
+        for (y, row) in grid.rows().enumerate() {
+            // Backgrounds.
+            for_each_run(row, |cell| cell.bg.map(|bg| displayed_color(bg, self.depth)), |x, cells, col| {
+                self.rect(buf, x, y, cells.len(), (0.0, 1.0), col)
+            })?;
+            // Blocks.
+            for_each_run(row, |cell| block_extent(cell.glyph).map(|extent| (extent, self.fg(cell))), |x, cells, (extent, col)| {
+                self.rect(buf, x, y, cells.len(), extent, col)
+            })?;
+            // Text, split at spaces and blocks.
+            for_each_run(row, |cell| (cell.glyph != ' ' && block_extent(cell.glyph).is_none()).then(|| self.fg(cell)), |x, cells, col| {
+                self.text(buf, x, y, cells, col)
+            })?;
+        }
+
+        write!(buf, "</g>\n</svg>\n")
+    }
+}
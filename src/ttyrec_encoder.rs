@@ -0,0 +1,33 @@
+use std::{io, time::Duration};
+
+use crate::{ansi_encoder::AnsiEncoder, encoder::AnimationEncoder, grid::Frame};
+
+/// Emits a ttyrec recording that replays the frames with their delays, e.g. with `ttyplay`.
+///
+/// Timestamps start at zero, players only look at the differences. A single grid is recorded as a single frame.
+pub struct TtyrecEncoder {
+    pub ansi: AnsiEncoder,
+}
+
+impl TtyrecEncoder {
+    pub fn new(ansi: AnsiEncoder) -> Self {
+        TtyrecEncoder { ansi }
+    }
+}
+
+/// Append a record: seconds, microseconds and length of the data as little endian 32 bit integers, then the data.
+fn push_record(buf: &mut Vec<u8>, time: Duration, data: &[u8]) {
+    buf.extend_from_slice(&(time.as_secs() as u32).to_le_bytes());
+    buf.extend_from_slice(&time.subsec_micros().to_le_bytes());
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+}
+
+impl AnimationEncoder for TtyrecEncoder {
+    fn encode_frames_into(&self, frames: &[Frame], buf: &mut Vec<u8>) -> io::Result<()> {
+        self.ansi.encode_recording(frames, |time, data| {
+            push_record(buf, time, data);
+            Ok(())
+        })
+    }
+}
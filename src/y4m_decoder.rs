@@ -0,0 +1,254 @@
+use std::{io::{self, BufRead, Read, Seek, SeekFrom}, time::Duration};
+
+use image::{error::{DecodingError, ImageFormatHint}, Delay, DynamicImage, ImageError, RgbImage};
+
+use crate::error::Result;
+
+/// Start of every YUV4MPEG2 stream.
+pub const MAGIC: &[u8] = b"YUV4MPEG2 ";
+
+/// Largest frame in pixels, so that a broken header doesn't allocate absurd amounts of memory.
+const MAX_PIXELS: u64 = 8192 * 8192;
+
+/// Chroma subsampling of the stream, as the size of a chroma sample in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Chroma {
+    Subsampled(u32, u32),
+    Mono,
+}
+
+/// Whether the stream starts like a YUV4MPEG2 video. The position of the stream is kept.
+pub fn is_y4m<R: BufRead + Seek>(reader: &mut R) -> io::Result<bool> {
+    let start = reader.stream_position()?;
+    let mut magic = Vec::with_capacity(MAGIC.len());
+    reader.by_ref().take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+    reader.seek(SeekFrom::Start(start))?;
+    Ok(magic == MAGIC)
+}
+
+fn error(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name(String::from("YUV4MPEG2")), message))
+}
+
+/// Parameters of a stream from its header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Header {
+    width: u32,
+    height: u32,
+    /// Frames per second, as a fraction.
+    rate: (u32, u32),
+    chroma: Chroma,
+}
+
+impl Header {
+    fn parse(line: &str) -> Result<Self> {
+        let mut header = Header { width: 0, height: 0, rate: (25, 1), chroma: Chroma::Subsampled(2, 2) };
+        for param in line.trim_end().split(' ').skip(1) {
+            let Some(tag) = param.chars().next() else { continue };
+            let value = &param[tag.len_utf8()..];
+            match tag {
+                'W' => header.width = value.parse().map_err(|_| error("invalid width"))?,
+                'H' => header.height = value.parse().map_err(|_| error("invalid height"))?,
+                'F' => {
+                    let (num, den) = value.split_once(':').ok_or_else(|| error("invalid frame rate"))?;
+                    header.rate = (num.parse::<u32>().map_err(|_| error("invalid frame rate"))?, den.parse::<u32>().map_err(|_| error("invalid frame rate"))?);
+                },
+                'C' => header.chroma = match value {
+                    "420" | "420jpeg" | "420paldv" | "420mpeg2" => Chroma::Subsampled(2, 2),
+                    "422" => Chroma::Subsampled(2, 1),
+                    "444" => Chroma::Subsampled(1, 1),
+                    "mono" => Chroma::Mono,
+                    _ => return Err(error(&format!("unsupported color space {value}")).into()),
+                },
+                _ => (),
+            }
+        }
+        if header.width == 0 || header.height == 0 || header.rate.0 == 0 || header.rate.1 == 0 {
+            return Err(error("missing or zero size or frame rate").into());
+        }
+        if u64::from(header.width) * u64::from(header.height) > MAX_PIXELS {
+            return Err(error("frame size too large").into());
+        }
+        Ok(header)
+    }
+
+    /// Number of bytes of the planes of one frame.
+    fn frame_size(&self) -> usize {
+        let luma_size = self.width as usize * self.height as usize;
+        let chroma_size = match self.chroma {
+            Chroma::Subsampled(cw, ch) => self.width.div_ceil(cw) as usize * self.height.div_ceil(ch) as usize,
+            Chroma::Mono => 0,
+        };
+        luma_size + 2 * chroma_size
+    }
+}
+
+/// Reads the frames of an 8 bit YUV4MPEG2 video as they arrive, as produced by `ffmpeg -f yuv4mpegpipe`.
+///
+/// The stream ends at the end of the input. A truncated last frame is dropped.
+pub struct Y4mFrames<R> {
+    reader: R,
+    header: Header,
+    planes: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> Y4mFrames<R> {
+    /// Read the header of the stream.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let header = Header::parse(&line)?;
+        Ok(Y4mFrames { reader, header, planes: vec![0; header.frame_size()], done: false })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.header.width, self.header.height)
+    }
+
+    /// Delay between frames according to the frame rate in the header.
+    pub fn delay(&self) -> Delay {
+        let (num, den) = self.header.rate;
+        Delay::from_saturating_duration(Duration::from_secs(u64::from(den)) / num)
+    }
+
+    fn read_frame(&mut self) -> Result<Option<DynamicImage>> {
+        let mut frame_header = String::new();
+        if self.reader.read_line(&mut frame_header)? == 0 {
+            return Ok(None);
+        }
+        if !frame_header.starts_with("FRAME") {
+            return Err(error("expected a frame").into());
+        }
+        match self.reader.read_exact(&mut self.planes) {
+            Ok(()) => (),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let Header { width, height, chroma, .. } = self.header;
+        Ok(Some(DynamicImage::ImageRgb8(to_rgb(&self.planes, width, height, chroma))))
+    }
+}
+
+impl<R: BufRead> Iterator for Y4mFrames<R> {
+    type Item = Result<DynamicImage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let frame = self.read_frame().transpose();
+        self.done = !matches!(frame, Some(Ok(_)));
+        frame
+    }
+}
+
+/// Convert the Y, Cb and Cr planes of a frame to RGB, with the BT.601 coefficients for limited range.
+fn to_rgb(planes: &[u8], width: u32, height: u32, chroma: Chroma) -> RgbImage {
+    let (luma, chroma_planes) = planes.split_at((width * height) as usize);
+    RgbImage::from_fn(width, height, |x, y| {
+        let l = 1.164 * (luma[(y * width + x) as usize] as f32 - 16.0);
+        let (cb, cr) = match chroma {
+            Chroma::Subsampled(cw, ch) => {
+                let chroma_size = chroma_planes.len() / 2;
+                let i = (y / ch * width.div_ceil(cw) + x / cw) as usize;
+                (chroma_planes[i] as f32 - 128.0, chroma_planes[chroma_size + i] as f32 - 128.0)
+            },
+            Chroma::Mono => (0.0, 0.0),
+        };
+        let r = l + 1.596 * cr;
+        let g = l - 0.392 * cb - 0.813 * cr;
+        let b = l + 2.017 * cb;
+        image::Rgb([r, g, b].map(|c| c.round().clamp(0.0, 255.0) as u8))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn header_gives_size_rate_and_chroma() {
+        let header = Header::parse("YUV4MPEG2 W64 H48 F30000:1001 Ip A1:1 C420jpeg XYSCSS=420JPEG\n").unwrap();
+        assert_eq!(header, Header { width: 64, height: 48, rate: (30000, 1001), chroma: Chroma::Subsampled(2, 2) });
+    }
+
+    #[test]
+    fn header_chroma_subsampling() {
+        for (tag, chroma) in [("C420", Chroma::Subsampled(2, 2)), ("C422", Chroma::Subsampled(2, 1)), ("C444", Chroma::Subsampled(1, 1)), ("Cmono", Chroma::Mono)] {
+            let header = Header::parse(&format!("YUV4MPEG2 W5 H3 F25:1 {tag}\n")).unwrap();
+            assert_eq!(header.chroma, chroma, "{tag}");
+        }
+    }
+
+    #[test]
+    fn header_defaults_to_420_at_25_fps() {
+        let header = Header::parse("YUV4MPEG2 W5 H3\n").unwrap();
+        assert_eq!((header.rate, header.chroma), ((25, 1), Chroma::Subsampled(2, 2)));
+    }
+
+    #[test]
+    fn header_rejects_unsupported_and_missing_params() {
+        for line in ["YUV4MPEG2 W5 H3 C420p10\n", "YUV4MPEG2 H3\n", "YUV4MPEG2 W5 H3 F0:1\n", "YUV4MPEG2 W5 H3 F30\n", "YUV4MPEG2 W100000 H100000\n"] {
+            assert!(Header::parse(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn delay_of_very_low_frame_rates() {
+        let frames = Y4mFrames::new(Cursor::new(b"YUV4MPEG2 W2 H2 F1:5000000 Cmono\n".to_vec())).unwrap();
+        assert!(Duration::from(frames.delay()) >= Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn frame_size_rounds_chroma_up() {
+        let header = |chroma| Header { width: 5, height: 3, rate: (25, 1), chroma };
+        assert_eq!(header(Chroma::Subsampled(2, 2)).frame_size(), 15 + 2 * 3 * 2);
+        assert_eq!(header(Chroma::Subsampled(2, 1)).frame_size(), 15 + 2 * 3 * 3);
+        assert_eq!(header(Chroma::Subsampled(1, 1)).frame_size(), 15 * 3);
+        assert_eq!(header(Chroma::Mono).frame_size(), 15);
+    }
+
+    #[test]
+    fn to_rgb_converts_limited_range() {
+        // Black, white, and the red of BT.601 limited range, without subsampling.
+        let planes = [16, 235, 81, 128, 128, 90, 128, 128, 240];
+        let image = to_rgb(&planes, 3, 1, Chroma::Subsampled(1, 1));
+        assert_eq!(image.pixels().map(|p| p.0).collect::<Vec<_>>(), [[0, 0, 0], [255, 255, 255], [254, 0, 0]]);
+    }
+
+    #[test]
+    fn to_rgb_shares_chroma_between_subsampled_pixels() {
+        // A 3x2 frame with 4:2:0 subsampling has 2x1 chroma samples, red on the left and gray on the right.
+        let planes = [81, 81, 126, 81, 81, 126, 90, 128, 240, 128];
+        let image = to_rgb(&planes, 3, 2, Chroma::Subsampled(2, 2));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if x < 2 {[254, 0, 0]} else {[128, 128, 128]};
+            assert_eq!(pixel.0, expected, "{x},{y}");
+        }
+    }
+
+    #[test]
+    fn to_rgb_mono_is_gray() {
+        let image = to_rgb(&[16, 126, 235], 3, 1, Chroma::Mono);
+        assert_eq!(image.pixels().map(|p| p.0).collect::<Vec<_>>(), [[0, 0, 0], [128, 128, 128], [255, 255, 255]]);
+    }
+
+    #[test]
+    fn frames_are_read_until_a_truncated_frame() {
+        let mut data = b"YUV4MPEG2 W2 H1 F30000:1001 Cmono\n".to_vec();
+        data.extend_from_slice(b"FRAME\n\x10\xeb");
+        data.extend_from_slice(b"FRAME Ixyz\n\xeb\x10");
+        data.extend_from_slice(b"FRAME\n\x10");
+        let frames = Y4mFrames::new(Cursor::new(data)).unwrap();
+        assert_eq!(frames.dimensions(), (2, 1));
+        assert_eq!(Duration::from(frames.delay()).as_micros(), 33366);
+
+        let frames: Vec<_> = frames.map(|frame| frame.unwrap().to_rgb8().into_raw()).collect();
+        assert_eq!(frames, [vec![0, 0, 0, 255, 255, 255], vec![255, 255, 255, 0, 0, 0]]);
+    }
+}